@@ -0,0 +1,153 @@
+//! Strips ANSI SGR (`\x1b[...m`) escape codes out of kernel stdout/stderr and
+//! captures the colors they selected as spans over the stripped text, the
+//! same shape [`crate::line_cache::StyleSpan`] uses for xi-core's styles so
+//! [`crate::edit_view::EditView`] can paint both with one code path.
+
+use druid::Color;
+
+/// A run of `color` applied to `[start, start + length)` of the stripped
+/// text returned alongside it by [`parse`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnsiSpan {
+    pub start: usize,
+    pub length: usize,
+    pub color: Color,
+}
+
+/// The 8 standard SGR foreground colors (30-37 / 90-97 bright variants).
+fn sgr_color(code: u16) -> Option<Color> {
+    let rgb = match code {
+        30 | 90 => (0x00, 0x00, 0x00),
+        31 | 91 => (0xcd, 0x31, 0x31),
+        32 | 92 => (0x0d, 0xbc, 0x79),
+        33 | 93 => (0xe5, 0xe5, 0x10),
+        34 | 94 => (0x24, 0x72, 0xc8),
+        35 | 95 => (0xbc, 0x3f, 0xbc),
+        36 | 96 => (0x11, 0xa8, 0xcd),
+        37 | 97 => (0xe5, 0xe5, 0xe5),
+        _ => return None,
+    };
+    Some(Color::rgb8(rgb.0, rgb.1, rgb.2))
+}
+
+/// Strips every CSI (`\x1b[...`) sequence out of `text`, returning the
+/// plain-text result plus the color spans the SGR (`...m`) ones selected.
+/// Only the foreground SGR codes are modeled; every other CSI sequence
+/// (background colors, bold/underline, cursor movement, line-clear) is
+/// recognized by its final byte and consumed without being turned into a
+/// span, rather than left in the output.
+pub fn parse(text: &str) -> (String, Vec<AnsiSpan>) {
+    let mut plain = String::with_capacity(text.len());
+    let mut spans = Vec::new();
+    let mut current: Option<Color> = None;
+    let mut span_start = 0usize;
+
+    let mut chars = text.char_indices().peekable();
+    while let Some((_, ch)) = chars.next() {
+        if ch != '\u{1b}' {
+            plain.push(ch);
+            continue;
+        }
+        if chars.peek().map(|&(_, c)| c) != Some('[') {
+            continue;
+        }
+        chars.next(); // consume '['
+
+        // A CSI sequence is `params` (0x30-0x3F) then `intermediates`
+        // (0x20-0x2F) then exactly one `final_byte` (0x40-0x7E); only `m`
+        // (SGR) carries colors we model, but any other final byte still
+        // terminates the sequence so it doesn't swallow the text after it.
+        let mut params = String::new();
+        let mut final_byte = None;
+        while let Some(&(_, c)) = chars.peek() {
+            chars.next();
+            if ('@'..='~').contains(&c) {
+                final_byte = Some(c);
+                break;
+            }
+            params.push(c);
+        }
+        let final_byte = match final_byte {
+            Some(c) => c,
+            None => continue, // ran off the end of text with no terminator
+        };
+        if final_byte != 'm' {
+            continue;
+        }
+
+        if let Some(color) = current.clone() {
+            if plain.len() > span_start {
+                spans.push(AnsiSpan {
+                    start: span_start,
+                    length: plain.len() - span_start,
+                    color,
+                });
+            }
+        }
+
+        // An empty parameter (as in `\x1b[m`) means 0, i.e. reset.
+        let codes = params.split(';').filter_map(|c| {
+            if c.is_empty() {
+                Some(0)
+            } else {
+                c.parse::<u16>().ok()
+            }
+        });
+        for code in codes {
+            if code == 0 {
+                current = None;
+            } else if let Some(color) = sgr_color(code) {
+                current = Some(color);
+            }
+        }
+        span_start = plain.len();
+    }
+
+    if let Some(color) = current {
+        if plain.len() > span_start {
+            spans.push(AnsiSpan {
+                start: span_start,
+                length: plain.len() - span_start,
+                color,
+            });
+        }
+    }
+
+    (plain, spans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_escapes_and_keeps_plain_text() {
+        let (plain, spans) = parse("\x1b[31merror\x1b[0m: bad token");
+        assert_eq!(plain, "error: bad token");
+        assert_eq!(
+            spans,
+            vec![AnsiSpan {
+                start: 0,
+                length: 5,
+                color: sgr_color(31).unwrap(),
+            }]
+        );
+    }
+
+    #[test]
+    fn unterminated_sequence_is_ignored() {
+        let (plain, spans) = parse("plain \x1b[31");
+        assert_eq!(plain, "plain ");
+        assert!(spans.is_empty());
+    }
+
+    #[test]
+    fn non_sgr_csi_sequence_does_not_swallow_following_text() {
+        // \x1b[2K is "erase in line", a common progress-bar escape; its
+        // final byte 'K' must terminate the sequence on its own, not leave
+        // "after" dangling as unconsumed params waiting for a literal 'm'.
+        let (plain, spans) = parse("\x1b[2Kafter");
+        assert_eq!(plain, "after");
+        assert!(spans.is_empty());
+    }
+}
@@ -0,0 +1,536 @@
+//! The widget that actually renders a xi-core buffer.
+//!
+//! `EditView` owns a [`LineCache`] that it keeps in sync with xi-core via
+//! `ApplyUpdate`/`ScrollTo` commands routed to it through [`EDIT_VIEW_COMMAND`],
+//! and forwards keyboard/mouse input back to xi-core as notifications.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, Weak};
+
+use druid::kurbo::Line as KurboLine;
+use druid::piet::{
+    FontFamily, ImageFormat, InterpolationMode, Text, TextLayout, TextLayoutBuilder,
+};
+use druid::{
+    BoxConstraints, Env, Event, EventCtx, KbKey, LayoutCtx, LifeCycle, LifeCycleCtx, PaintCtx,
+    Point, Rect, RenderContext, Selector, Size, UpdateCtx, Widget,
+};
+use image::GenericImageView;
+use serde_json::{json, Value};
+
+use crate::execution::{ExecutionStore, OutputBlock, OutputStatus};
+use crate::kernel::KernelManager;
+use crate::line_cache::LineCache;
+use crate::rpc::Core;
+use crate::theme::{self, StyleDef};
+use crate::{EditViewCommands, ViewId, ViewState};
+
+/// Routes an [`EditViewCommands`] value to the `EditView` with the matching
+/// `WidgetId`. The app-level dispatcher submits this from the xi-core RPC
+/// thread via `ExtEventSink::submit_command`.
+pub const EDIT_VIEW_COMMAND: Selector<EditViewCommands> =
+    Selector::new("druid-xi.edit-view-command");
+
+const LINE_HEIGHT: f64 = 17.0;
+const FONT_SIZE: f64 = 13.0;
+const LEFT_PADDING: f64 = 6.0;
+/// Output blocks are capped to this many source-line-heights tall, so a
+/// large image or a long traceback doesn't dwarf the code above it.
+const MAX_OUTPUT_HEIGHT: f64 = LINE_HEIGHT * 8.0;
+/// xi-core reserves style id 1 for find-match highlighting (id 0 is the
+/// selection); unlike syntax styles it isn't carried in `theme_changed`, so
+/// `EditView` paints it with its own background color instead of resolving
+/// it through `styles`.
+const FIND_HIGHLIGHT_STYLE_ID: usize = 1;
+const FIND_HIGHLIGHT_COLOR: druid::Color = druid::Color::rgba8(0xff, 0xd5, 0x4f, 0x80);
+
+pub struct EditView {
+    view_id: ViewId,
+    core: Weak<Mutex<Core>>,
+    kernels: Weak<Mutex<KernelManager>>,
+    line_cache: LineCache,
+    /// First line drawn at the top of the viewport, in document line units.
+    first_visible_line: usize,
+    /// The current theme's numeric style id -> color/font-style map, used to
+    /// resolve each line's `styles` spans. Base colors live in the `Env`
+    /// instead (see `crate::theme`), since every widget can read those.
+    styles: HashMap<usize, StyleDef>,
+    /// Output blocks from `Execute` runs, keyed by the anchor line they're
+    /// rendered beneath.
+    outputs: ExecutionStore,
+}
+
+impl EditView {
+    pub fn new(
+        view_id: ViewId,
+        core: Weak<Mutex<Core>>,
+        kernels: Weak<Mutex<KernelManager>>,
+    ) -> EditView {
+        EditView {
+            view_id,
+            core,
+            kernels,
+            line_cache: LineCache::new(),
+            first_visible_line: 0,
+            styles: HashMap::new(),
+            outputs: ExecutionStore::new(),
+        }
+    }
+
+    fn visible_line_count(&self, size: Size) -> usize {
+        (size.height / LINE_HEIGHT).ceil() as usize + 1
+    }
+
+    fn send_notification(&self, method: &str, params: Value) {
+        if let Some(core) = self.core.upgrade() {
+            let mut params = params;
+            params["view_id"] = json!(self.view_id);
+            core.lock().unwrap().send_notification(method, &params);
+        }
+    }
+
+    /// Converts a click's horizontal position into the byte column within
+    /// `line`, the reverse of the `hit_test_text_position` calls `paint`
+    /// uses to place spans/cursors — built from the same font so the two
+    /// stay consistent. Falls back to column 0 for a line not yet fetched.
+    fn hit_test_col(&self, ctx: &mut EventCtx, line: usize, x: f64) -> usize {
+        let text = match self.line_cache.get(line) {
+            Some(line) => line.text.clone(),
+            None => return 0,
+        };
+        let layout = ctx
+            .text()
+            .new_text_layout(text)
+            .font(FontFamily::MONOSPACE, FONT_SIZE)
+            .text_color(druid::Color::BLACK)
+            .build()
+            .unwrap();
+        layout
+            .hit_test_point(Point::new((x - LEFT_PADDING).max(0.0), 0.0))
+            .idx
+    }
+
+    /// Runs the text of the line the primary cursor sits on through the
+    /// current language's kernel, anchoring its output beneath that line.
+    /// There's no multi-line selection tracking yet (see `line_cache::Line`),
+    /// so a single line is the whole "region" `Execute` can run.
+    fn execute_current_line(&mut self) {
+        let anchor_line = match (0..self.line_cache.height()).find(|&ix| {
+            self.line_cache
+                .get(ix)
+                .map(|line| !line.cursors.is_empty())
+                .unwrap_or(false)
+        }) {
+            Some(ix) => ix,
+            None => return,
+        };
+        let code = match self.line_cache.get(anchor_line) {
+            Some(line) => line.text.clone(),
+            None => return,
+        };
+        self.outputs.start(anchor_line);
+        if let Some(kernels) = self.kernels.upgrade() {
+            kernels.lock().unwrap().execute(anchor_line, code);
+        }
+    }
+
+    /// Applies a command delivered through [`EDIT_VIEW_COMMAND`].
+    fn poke(&mut self, cmd: &EditViewCommands, ctx: &mut EventCtx) {
+        match cmd {
+            EditViewCommands::ViewId(view_id) => {
+                self.view_id = view_id.clone();
+            }
+            EditViewCommands::ApplyUpdate(update) => {
+                self.line_cache.apply_update(update);
+                ctx.request_layout();
+                ctx.request_paint();
+            }
+            EditViewCommands::ScrollTo(line) => {
+                self.first_visible_line = *line;
+                ctx.request_paint();
+            }
+            EditViewCommands::ApplyTheme(theme) => {
+                self.styles = theme.styles.clone();
+                ctx.request_paint();
+            }
+            EditViewCommands::Execute => {
+                self.execute_current_line();
+                ctx.request_paint();
+            }
+            EditViewCommands::KernelMessage {
+                anchor_line,
+                message,
+            } => {
+                self.outputs.apply(*anchor_line, message.clone());
+                ctx.request_layout();
+                ctx.request_paint();
+            }
+            _ => (),
+        }
+    }
+
+    /// Lays out each visible source line's top `y` plus, for lines with an
+    /// output block anchored to them, the rect that block occupies
+    /// immediately below it. Output blocks push every following line down,
+    /// so both painting and mouse hit-testing walk this same layout.
+    fn visible_rows(&self, size: Size) -> Vec<(usize, f64, Option<Rect>)> {
+        let visible = self.visible_line_count(size);
+        let last = (self.first_visible_line + visible).min(self.line_cache.height());
+        let mut rows = Vec::with_capacity(last.saturating_sub(self.first_visible_line));
+        let mut y = 0.0;
+        for ix in self.first_visible_line..last {
+            if y > size.height {
+                break;
+            }
+            let line_y = y;
+            y += LINE_HEIGHT;
+            let output_rect = self.outputs.get(ix).map(|output| {
+                let height = if output.collapsed {
+                    LINE_HEIGHT
+                } else {
+                    MAX_OUTPUT_HEIGHT
+                };
+                let rect =
+                    Rect::from_origin_size(Point::new(0.0, y), Size::new(size.width, height));
+                y += height;
+                rect
+            });
+            rows.push((ix, line_y, output_rect));
+        }
+        rows
+    }
+
+    /// Renders the output block anchored below `anchor_line` into `rect`.
+    /// Collapsed blocks draw only a one-line summary; a `Running` block with
+    /// no output yet draws a spinner in its place.
+    fn paint_output(
+        &self,
+        ctx: &mut PaintCtx,
+        anchor_line: usize,
+        rect: Rect,
+        foreground: &druid::Color,
+    ) {
+        let output = match self.outputs.get(anchor_line) {
+            Some(output) => output,
+            None => return,
+        };
+
+        if output.collapsed {
+            let summary = format!(
+                "[{} output block(s) — click to expand]",
+                output.blocks.len()
+            );
+            let layout = ctx
+                .text()
+                .new_text_layout(summary)
+                .font(FontFamily::MONOSPACE, FONT_SIZE)
+                .text_color(foreground.clone())
+                .build()
+                .unwrap();
+            ctx.draw_text(&layout, Point::new(LEFT_PADDING, rect.y0));
+            return;
+        }
+
+        let mut y = rect.y0;
+        if output.status == OutputStatus::Running && output.blocks.is_empty() {
+            let spinner = ctx
+                .text()
+                .new_text_layout("\u{231b} running…")
+                .font(FontFamily::MONOSPACE, FONT_SIZE)
+                .text_color(foreground.clone())
+                .build()
+                .unwrap();
+            ctx.draw_text(&spinner, Point::new(LEFT_PADDING, y));
+            return;
+        }
+
+        for block in &output.blocks {
+            if y >= rect.y1 {
+                break;
+            }
+            match block {
+                OutputBlock::Text { plain, spans } => {
+                    y += self.draw_ansi_text(ctx, plain, spans, y, foreground);
+                }
+                OutputBlock::Error {
+                    ename,
+                    evalue,
+                    traceback,
+                } => {
+                    let header = format!("{}: {}", ename, evalue);
+                    let (header_plain, header_spans) = crate::ansi::parse(&header);
+                    y += self.draw_ansi_text(ctx, &header_plain, &header_spans, y, foreground);
+                    for (line, spans) in traceback {
+                        if y >= rect.y1 {
+                            break;
+                        }
+                        y += self.draw_ansi_text(ctx, line, spans, y, foreground);
+                    }
+                }
+                OutputBlock::Image { bytes } => {
+                    y += self.draw_image(ctx, bytes, y, rect.width(), rect.y1 - y);
+                }
+            }
+        }
+    }
+
+    /// Draws one line of text, overlaying any ANSI-derived color spans the
+    /// same way `paint` overlays xi-core style spans, and returns the height
+    /// it consumed.
+    fn draw_ansi_text(
+        &self,
+        ctx: &mut PaintCtx,
+        text: &str,
+        spans: &[crate::ansi::AnsiSpan],
+        y: f64,
+        foreground: &druid::Color,
+    ) -> f64 {
+        let base_layout = ctx
+            .text()
+            .new_text_layout(text.to_string())
+            .font(FontFamily::MONOSPACE, FONT_SIZE)
+            .text_color(foreground.clone())
+            .build()
+            .unwrap();
+        ctx.draw_text(&base_layout, Point::new(LEFT_PADDING, y));
+
+        for span in spans {
+            let end = (span.start + span.length).min(text.len());
+            if span.start >= end {
+                continue;
+            }
+            let x = LEFT_PADDING + base_layout.hit_test_text_position(span.start).point.x;
+            let span_layout = ctx
+                .text()
+                .new_text_layout(text[span.start..end].to_string())
+                .font(FontFamily::MONOSPACE, FONT_SIZE)
+                .text_color(span.color.clone())
+                .build()
+                .unwrap();
+            ctx.draw_text(&span_layout, Point::new(x, y));
+        }
+        LINE_HEIGHT
+    }
+
+    /// Decodes `bytes` (PNG or JPEG) and draws it scaled to fit within
+    /// `max_width`, capped to the output block's remaining height
+    /// (`max_height`), returning the height it consumed.
+    fn draw_image(
+        &self,
+        ctx: &mut PaintCtx,
+        bytes: &[u8],
+        y: f64,
+        max_width: f64,
+        max_height: f64,
+    ) -> f64 {
+        let decoded = match image::load_from_memory(bytes) {
+            Ok(image) => image.to_rgba8(),
+            Err(_) => return LINE_HEIGHT,
+        };
+        let (width, height) = decoded.dimensions();
+        let scale = (max_width / width as f64)
+            .min(max_height / height as f64)
+            .min(1.0);
+        let draw_size = Size::new(width as f64 * scale, height as f64 * scale);
+
+        let piet_image = ctx
+            .make_image(
+                width as usize,
+                height as usize,
+                decoded.as_raw(),
+                ImageFormat::RgbaSeparate,
+            )
+            .unwrap();
+        ctx.draw_image(
+            &piet_image,
+            Rect::from_origin_size(Point::new(LEFT_PADDING, y), draw_size),
+            InterpolationMode::Bilinear,
+        );
+        draw_size.height
+    }
+}
+
+impl Widget<ViewState> for EditView {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, _data: &mut ViewState, _env: &Env) {
+        match event {
+            Event::Command(cmd) => {
+                if let Some(payload) = cmd.get(EDIT_VIEW_COMMAND) {
+                    self.poke(payload, ctx);
+                }
+            }
+            Event::KeyDown(key_event) => {
+                ctx.request_focus();
+                match &key_event.key {
+                    KbKey::Backspace => self.send_notification("delete_backward", json!({})),
+                    KbKey::Enter => self.send_notification("insert", json!({ "chars": "\n" })),
+                    KbKey::Character(chars) => {
+                        self.send_notification("insert", json!({ "chars": chars }))
+                    }
+                    _ => (),
+                }
+            }
+            Event::MouseDown(mouse_event) => {
+                ctx.request_focus();
+                let size = ctx.size();
+                let clicked_output =
+                    self.visible_rows(size)
+                        .into_iter()
+                        .find_map(|(ix, _, rect)| {
+                            rect.filter(|rect| rect.contains(mouse_event.pos))
+                                .map(|_| ix)
+                        });
+                if let Some(anchor_line) = clicked_output {
+                    if self.outputs.toggle_collapsed(anchor_line) {
+                        ctx.request_layout();
+                        ctx.request_paint();
+                    }
+                    return;
+                }
+                ctx.set_active(true);
+                let line = self
+                    .visible_rows(size)
+                    .into_iter()
+                    .rev()
+                    .find(|&(_, y, _)| y <= mouse_event.pos.y)
+                    .map(|(ix, _, _)| ix)
+                    .unwrap_or(self.first_visible_line);
+                let col = self.hit_test_col(ctx, line, mouse_event.pos.x);
+                self.send_notification(
+                    "gesture",
+                    json!({ "line": line, "col": col, "ty": "point_select" }),
+                );
+            }
+            Event::MouseUp(_) => ctx.set_active(false),
+            Event::Wheel(mouse_event) => {
+                let delta_lines = (mouse_event.wheel_delta.y / LINE_HEIGHT) as i64;
+                let max_first = self.line_cache.height().saturating_sub(1);
+                self.first_visible_line = (self.first_visible_line as i64 + delta_lines)
+                    .max(0)
+                    .min(max_first as i64) as usize;
+                ctx.request_paint();
+                // "height" is the viewport's visible line count, not the
+                // per-event scroll delta — xi-core uses `first`/`height`
+                // together to know which line range is on screen.
+                self.send_notification(
+                    "scroll",
+                    json!({
+                        "first": self.first_visible_line,
+                        "height": self.visible_line_count(ctx.size()),
+                    }),
+                );
+            }
+            _ => (),
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        _ctx: &mut LifeCycleCtx,
+        _event: &LifeCycle,
+        _data: &ViewState,
+        _env: &Env,
+    ) {
+    }
+
+    fn update(
+        &mut self,
+        _ctx: &mut UpdateCtx,
+        _old_data: &ViewState,
+        _data: &ViewState,
+        _env: &Env,
+    ) {
+    }
+
+    fn layout(
+        &mut self,
+        _ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        _data: &ViewState,
+        _env: &Env,
+    ) -> Size {
+        bc.max()
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, _data: &ViewState, env: &Env) {
+        let size = ctx.size();
+        let foreground = env.get(theme::EDITOR_FOREGROUND);
+        let caret_color = env.get(theme::EDITOR_CARET);
+        ctx.fill(
+            Rect::from_origin_size(Point::ORIGIN, size),
+            &env.get(theme::EDITOR_BACKGROUND),
+        );
+
+        for (ix, y, output_rect) in self.visible_rows(size) {
+            let line = match self.line_cache.get(ix) {
+                Some(line) => line,
+                // Not yet fetched: xi-core will send it once it notices we
+                // scrolled past an invalidated range.
+                None => continue,
+            };
+
+            // Build one reference layout in the base foreground color so
+            // cursor/span positions can be measured by byte offset, then
+            // overdraw any styled spans in their resolved color.
+            let base_layout = ctx
+                .text()
+                .new_text_layout(line.text.clone())
+                .font(FontFamily::MONOSPACE, FONT_SIZE)
+                .text_color(foreground.clone())
+                .build()
+                .unwrap();
+
+            // Find-match backgrounds are painted before the text so the
+            // glyphs drawn afterward sit on top of them.
+            for span in &line.styles {
+                if span.style_id != FIND_HIGHLIGHT_STYLE_ID {
+                    continue;
+                }
+                let end = (span.start + span.length).min(line.text.len());
+                if span.start >= end {
+                    continue;
+                }
+                let x0 = LEFT_PADDING + base_layout.hit_test_text_position(span.start).point.x;
+                let x1 = LEFT_PADDING + base_layout.hit_test_text_position(end).point.x;
+                ctx.fill(Rect::new(x0, y, x1, y + LINE_HEIGHT), &FIND_HIGHLIGHT_COLOR);
+            }
+
+            ctx.draw_text(&base_layout, Point::new(LEFT_PADDING, y));
+
+            for span in &line.styles {
+                if span.style_id == FIND_HIGHLIGHT_STYLE_ID {
+                    continue;
+                }
+                let style = match self.styles.get(&span.style_id).and_then(|s| s.fg.as_ref()) {
+                    Some(fg) => fg,
+                    None => continue,
+                };
+                let end = (span.start + span.length).min(line.text.len());
+                if span.start >= end {
+                    continue;
+                }
+                let x = LEFT_PADDING + base_layout.hit_test_text_position(span.start).point.x;
+                let span_layout = ctx
+                    .text()
+                    .new_text_layout(line.text[span.start..end].to_string())
+                    .font(FontFamily::MONOSPACE, FONT_SIZE)
+                    .text_color(style.clone())
+                    .build()
+                    .unwrap();
+                ctx.draw_text(&span_layout, Point::new(x, y));
+            }
+
+            for &cursor in &line.cursors {
+                let x = LEFT_PADDING + base_layout.hit_test_text_position(cursor).point.x;
+                ctx.stroke(
+                    KurboLine::new(Point::new(x, y), Point::new(x, y + LINE_HEIGHT)),
+                    &caret_color,
+                    1.0,
+                );
+            }
+
+            if let Some(rect) = output_rect {
+                self.paint_output(ctx, ix, rect, &foreground);
+            }
+        }
+    }
+}
@@ -0,0 +1,199 @@
+//! Tracks the output block rendered beneath each line range that's been run
+//! through a kernel, keyed by the line the run started on (its "anchor").
+//! [`crate::edit_view::EditView`] owns one [`ExecutionStore`] and feeds it
+//! [`crate::kernel::KernelMessage`]s as they arrive.
+
+use std::collections::HashMap;
+
+use crate::ansi;
+use crate::kernel::{KernelMessage, MimeBundle};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputStatus {
+    Running,
+    Done,
+}
+
+/// One piece of a run's output, in the order the kernel produced it.
+#[derive(Debug, Clone)]
+pub enum OutputBlock {
+    /// `stream`/`execute_result` text, already stripped of SGR escapes with
+    /// the color spans they selected kept alongside it.
+    Text {
+        plain: String,
+        spans: Vec<ansi::AnsiSpan>,
+    },
+    Image {
+        bytes: Vec<u8>,
+    },
+    Error {
+        ename: String,
+        evalue: String,
+        traceback: Vec<(String, Vec<ansi::AnsiSpan>)>,
+    },
+}
+
+/// The collapsible block anchored below `anchor_line`.
+#[derive(Debug, Clone)]
+pub struct ExecutionOutput {
+    pub status: OutputStatus,
+    pub collapsed: bool,
+    pub blocks: Vec<OutputBlock>,
+}
+
+impl ExecutionOutput {
+    fn new() -> ExecutionOutput {
+        ExecutionOutput {
+            status: OutputStatus::Running,
+            collapsed: false,
+            blocks: Vec::new(),
+        }
+    }
+
+    fn push_bundle(&mut self, bundle: MimeBundle) {
+        if let Some(png) = bundle.image_png.or(bundle.image_jpeg) {
+            self.blocks.push(OutputBlock::Image { bytes: png });
+        } else if let Some(text) = bundle.text_plain {
+            let (plain, spans) = ansi::parse(&text);
+            self.blocks.push(OutputBlock::Text { plain, spans });
+        }
+    }
+}
+
+/// Maps each executed region's anchor line to its output block. Anchors are
+/// plain line indices, so a block is orphaned (but left in place) if later
+/// edits shift the lines around it; re-running the region fixes this the
+/// same way re-running any cell does in a notebook.
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionStore {
+    outputs: HashMap<usize, ExecutionOutput>,
+}
+
+impl ExecutionStore {
+    pub fn new() -> ExecutionStore {
+        ExecutionStore::default()
+    }
+
+    pub fn get(&self, anchor_line: usize) -> Option<&ExecutionOutput> {
+        self.outputs.get(&anchor_line)
+    }
+
+    /// Clears whatever a previous run left at `anchor_line` and marks a new
+    /// run as in progress there.
+    pub fn start(&mut self, anchor_line: usize) {
+        self.outputs.insert(anchor_line, ExecutionOutput::new());
+    }
+
+    pub fn toggle_collapsed(&mut self, anchor_line: usize) -> bool {
+        match self.outputs.get_mut(&anchor_line) {
+            Some(output) => {
+                output.collapsed = !output.collapsed;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Applies one [`KernelMessage`] to the block at `anchor_line`, if a run
+    /// was started there. Messages for an anchor that was never started are
+    /// dropped. Runs carry no id of their own, so late replies from an
+    /// earlier run at an anchor that has since been re-started are applied
+    /// to the new block.
+    pub fn apply(&mut self, anchor_line: usize, message: KernelMessage) {
+        let output = match self.outputs.get_mut(&anchor_line) {
+            Some(output) => output,
+            None => return,
+        };
+        match message {
+            KernelMessage::Stream { text } => {
+                let (plain, spans) = ansi::parse(&text);
+                output.blocks.push(OutputBlock::Text { plain, spans });
+            }
+            KernelMessage::ExecuteResult(bundle) | KernelMessage::DisplayData(bundle) => {
+                output.push_bundle(bundle);
+            }
+            KernelMessage::Error {
+                ename,
+                evalue,
+                traceback,
+            } => {
+                let traceback = traceback.iter().map(|line| ansi::parse(line)).collect();
+                output.blocks.push(OutputBlock::Error {
+                    ename,
+                    evalue,
+                    traceback,
+                });
+            }
+            KernelMessage::Busy => output.status = OutputStatus::Running,
+            KernelMessage::Idle => output.status = OutputStatus::Done,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel::MimeBundle;
+
+    #[test]
+    fn apply_ignores_messages_for_an_anchor_that_was_never_started() {
+        let mut store = ExecutionStore::new();
+        store.apply(3, KernelMessage::Idle);
+        assert!(store.get(3).is_none());
+    }
+
+    #[test]
+    fn apply_collects_stream_output_and_tracks_busy_idle() {
+        let mut store = ExecutionStore::new();
+        store.start(5);
+        store.apply(5, KernelMessage::Busy);
+        store.apply(
+            5,
+            KernelMessage::Stream {
+                text: "hello\n".to_string(),
+            },
+        );
+        store.apply(5, KernelMessage::Idle);
+
+        let output = store.get(5).unwrap();
+        assert_eq!(output.status, OutputStatus::Done);
+        assert_eq!(output.blocks.len(), 1);
+        match &output.blocks[0] {
+            OutputBlock::Text { plain, .. } => assert_eq!(plain, "hello\n"),
+            other => panic!("expected Text block, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn apply_prefers_image_over_text_in_a_mime_bundle() {
+        let mut store = ExecutionStore::new();
+        store.start(1);
+        store.apply(
+            1,
+            KernelMessage::ExecuteResult(MimeBundle {
+                text_plain: Some("42".to_string()),
+                image_png: Some(vec![1, 2, 3]),
+                image_jpeg: None,
+            }),
+        );
+
+        match &store.get(1).unwrap().blocks[0] {
+            OutputBlock::Image { bytes } => assert_eq!(bytes, &vec![1u8, 2, 3]),
+            other => panic!("expected Image block, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn start_clears_a_previous_run_at_the_same_anchor() {
+        let mut store = ExecutionStore::new();
+        store.start(2);
+        store.apply(
+            2,
+            KernelMessage::Stream {
+                text: "stale".to_string(),
+            },
+        );
+        store.start(2);
+        assert!(store.get(2).unwrap().blocks.is_empty());
+    }
+}
@@ -0,0 +1,138 @@
+//! The incremental find/replace bar layered above the `EditView`.
+//!
+//! The bar only edits `ViewState`'s `find_*` fields and posts
+//! `EditViewCommands` through [`crate::menu::APP_VIEW_COMMAND`]; it never
+//! talks to xi-core directly, matching how the menu dispatches commands.
+
+use druid::widget::{Button, Checkbox, Flex, Label, TextBox};
+use druid::{Env, Event, EventCtx, Selector, UpdateCtx, Widget, WidgetExt};
+
+use crate::menu::APP_VIEW_COMMAND;
+use crate::{EditViewCommands, ViewState};
+
+/// Toggles `ViewState::find_open`; handled directly by the `AppDelegate`
+/// since it's pure UI state that never needs to reach xi-core.
+pub const TOGGLE_FIND: Selector<()> = Selector::new("druid-xi.toggle-find");
+
+pub fn build_search_bar() -> impl Widget<ViewState> {
+    let query = TextBox::new()
+        .with_placeholder("Find")
+        .lens(ViewState::find_query)
+        .controller(FindController)
+        .expand_width();
+
+    let case_sensitive = Checkbox::new("Aa").lens(ViewState::find_case_sensitive);
+    let regex = Checkbox::new(".*").lens(ViewState::find_regex);
+    let whole_words = Checkbox::new("\u{201c}\u{201d}").lens(ViewState::find_whole_words);
+    let matches = Label::new(|data: &ViewState, _: &Env| {
+        if data.find_query.is_empty() {
+            String::new()
+        } else {
+            format!("{} matches", data.find_matches)
+        }
+    });
+
+    let replacement = TextBox::new()
+        .with_placeholder("Replace")
+        .lens(ViewState::find_replacement)
+        .expand_width();
+    let replace_next = Button::new("Replace").on_click(|ctx, data: &mut ViewState, _| {
+        submit_replacement(ctx, data);
+        ctx.submit_command(APP_VIEW_COMMAND.with(EditViewCommands::ReplaceNext));
+    });
+    let replace_all = Button::new("Replace All").on_click(|ctx, data: &mut ViewState, _| {
+        submit_replacement(ctx, data);
+        ctx.submit_command(APP_VIEW_COMMAND.with(EditViewCommands::ReplaceAll));
+    });
+
+    Flex::column()
+        .with_child(
+            Flex::row()
+                .with_flex_child(query, 1.0)
+                .with_spacer(4.0)
+                .with_child(case_sensitive)
+                .with_child(regex)
+                .with_child(whole_words)
+                .with_spacer(4.0)
+                .with_child(matches),
+        )
+        .with_child(
+            Flex::row()
+                .with_flex_child(replacement, 1.0)
+                .with_spacer(4.0)
+                .with_child(replace_next)
+                .with_spacer(4.0)
+                .with_child(replace_all),
+        )
+        .padding(4.0)
+}
+
+/// Tells xi-core what to replace matches with, the way `FindController`
+/// tells it what to search for; `ReplaceNext`/`ReplaceAll` only apply
+/// whatever replacement string was last set this way.
+fn submit_replacement(ctx: &mut EventCtx, data: &ViewState) {
+    ctx.submit_command(
+        APP_VIEW_COMMAND.with(EditViewCommands::Replace(data.find_replacement.clone())),
+    );
+}
+
+/// Translates Enter/Shift+Enter in the query box into `Find`/`FindNext`/
+/// `FindPrevious` instead of inserting a newline, and re-runs `Find` as the
+/// query or its options change so matches/highlights stay live.
+struct FindController;
+
+impl<W: Widget<ViewState>> druid::widget::Controller<ViewState, W> for FindController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut ViewState,
+        env: &Env,
+    ) {
+        if let Event::KeyDown(key_event) = event {
+            if key_event.key == druid::KbKey::Enter {
+                let cmd = if key_event.mods.shift() {
+                    EditViewCommands::FindPrevious
+                } else if data.find_query.is_empty() {
+                    child.event(ctx, event, data, env);
+                    return;
+                } else {
+                    EditViewCommands::FindNext
+                };
+                ctx.submit_command(APP_VIEW_COMMAND.with(cmd));
+                ctx.set_handled();
+                return;
+            }
+            if key_event.key == druid::KbKey::Escape {
+                data.find_open = false;
+                ctx.set_handled();
+                return;
+            }
+        }
+        child.event(ctx, event, data, env);
+    }
+
+    fn update(
+        &mut self,
+        child: &mut W,
+        ctx: &mut UpdateCtx,
+        old_data: &ViewState,
+        data: &ViewState,
+        env: &Env,
+    ) {
+        let query_changed = old_data.find_query != data.find_query
+            || old_data.find_case_sensitive != data.find_case_sensitive
+            || old_data.find_regex != data.find_regex
+            || old_data.find_whole_words != data.find_whole_words;
+        if query_changed && !data.find_query.is_empty() {
+            ctx.submit_command(APP_VIEW_COMMAND.with(EditViewCommands::Find {
+                query: data.find_query.clone(),
+                case_sensitive: data.find_case_sensitive,
+                regex: data.find_regex,
+                whole_words: data.find_whole_words,
+            }));
+        }
+        child.update(ctx, old_data, data, env);
+    }
+}
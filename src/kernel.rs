@@ -0,0 +1,335 @@
+//! A minimal execution client speaking a stdio-JSON protocol *inspired by*
+//! Jupyter's message types, not real Jupyter kernels: actual Jupyter kernels
+//! talk ZeroMQ to a connection file, with HMAC-signed multipart messages,
+//! which is out of scope here. A registry maps buffer languages to the
+//! command line of a small adapter process that speaks this app's line-
+//! delimited JSON instead — see [`KernelRegistry::known_spec`] for the
+//! adapter names it expects on `PATH` — and a manager spawns one adapter
+//! process per run, feeds it an `execute_request` on its stdin, and streams
+//! the resulting `stream`/`execute_result`/`display_data`/`error` messages it
+//! prints on stdout back to the focused [`crate::edit_view::EditView`] the
+//! same way [`crate::rpc::Core`]'s replies reach it — as an
+//! [`crate::edit_view::EDIT_VIEW_COMMAND`] submitted through the app's
+//! `ExtEventSink`.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+
+use base64::Engine;
+use druid::{ExtEventSink, Target, WidgetId};
+use serde_json::Value;
+
+use crate::edit_view::EDIT_VIEW_COMMAND;
+use crate::EditViewCommands;
+
+/// The command line used to launch the kernel for one language.
+#[derive(Debug, Clone)]
+pub struct KernelSpec {
+    pub language: String,
+    pub cmd: String,
+    pub args: Vec<String>,
+}
+
+/// Known kernels, keyed by the `languageid` xi-core reports in
+/// `language_changed`/`available_languages`. Unrecognized languages simply
+/// have no entry, so `Execute` is a no-op for them.
+#[derive(Debug, Clone, Default)]
+pub struct KernelRegistry {
+    specs: HashMap<String, KernelSpec>,
+}
+
+impl KernelRegistry {
+    /// The adapter command for one language: a small per-language process,
+    /// expected on `PATH`, that reads a single `execute_request` JSON object
+    /// from stdin and writes line-delimited `KernelMessage`-shaped JSON
+    /// objects to stdout — this app's own stdio protocol, not a real Jupyter
+    /// kernel wire format. Pointing these at `python3`/`evcxr_jupyter`/etc.
+    /// directly would just hang, since those speak ZeroMQ, not stdio.
+    fn known_spec(language: &str) -> Option<KernelSpec> {
+        let (cmd, args): (&str, &[&str]) = match language {
+            "python" | "python3" => ("druid-xi-kernel-adapter", &["--lang", "python3"]),
+            "rust" => ("druid-xi-kernel-adapter", &["--lang", "rust"]),
+            "javascript" | "typescript" => ("druid-xi-kernel-adapter", &["--lang", "javascript"]),
+            _ => return None,
+        };
+        Some(KernelSpec {
+            language: language.to_string(),
+            cmd: cmd.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+        })
+    }
+
+    /// Registers every language in `available_languages` that we recognize
+    /// a kernel for; called once xi-core reports the set it knows about.
+    pub fn register_known(&mut self, languages: &[String]) {
+        for language in languages {
+            if let Some(spec) = Self::known_spec(language) {
+                self.specs.insert(language.clone(), spec);
+            }
+        }
+    }
+
+    pub fn get(&self, language: &str) -> Option<&KernelSpec> {
+        self.specs.get(language)
+    }
+}
+
+/// A parsed IOPub reply, stripped down to the fields `EditView` renders.
+#[derive(Debug, Clone)]
+pub enum KernelMessage {
+    Stream {
+        text: String,
+    },
+    ExecuteResult(MimeBundle),
+    DisplayData(MimeBundle),
+    Error {
+        ename: String,
+        evalue: String,
+        traceback: Vec<String>,
+    },
+    Busy,
+    Idle,
+}
+
+/// The subset of a Jupyter MIME bundle this app knows how to render.
+#[derive(Debug, Clone, Default)]
+pub struct MimeBundle {
+    pub text_plain: Option<String>,
+    pub image_png: Option<Vec<u8>>,
+    pub image_jpeg: Option<Vec<u8>>,
+}
+
+impl MimeBundle {
+    fn from_json(data: &Value) -> MimeBundle {
+        let decode = |field: &str| {
+            data[field]
+                .as_str()
+                .and_then(|b64| base64::engine::general_purpose::STANDARD.decode(b64).ok())
+        };
+        MimeBundle {
+            text_plain: data["text/plain"].as_str().map(str::to_string),
+            image_png: decode("image/png"),
+            image_jpeg: decode("image/jpeg"),
+        }
+    }
+}
+
+impl KernelMessage {
+    /// Parses one line of the kernel's stdout, which this app expects to be
+    /// a single JSON object shaped like a Jupyter IOPub message:
+    /// `{"msg_type": "...", "content": {...}}`.
+    fn from_json(line: &Value) -> Option<KernelMessage> {
+        let msg_type = line["msg_type"].as_str()?;
+        let content = &line["content"];
+        Some(match msg_type {
+            "stream" => KernelMessage::Stream {
+                text: content["text"].as_str().unwrap_or_default().to_string(),
+            },
+            "execute_result" => {
+                KernelMessage::ExecuteResult(MimeBundle::from_json(&content["data"]))
+            }
+            "display_data" => KernelMessage::DisplayData(MimeBundle::from_json(&content["data"])),
+            "error" => KernelMessage::Error {
+                ename: content["ename"].as_str().unwrap_or_default().to_string(),
+                evalue: content["evalue"].as_str().unwrap_or_default().to_string(),
+                traceback: content["traceback"]
+                    .as_array()
+                    .map(|lines| {
+                        lines
+                            .iter()
+                            .filter_map(|l| l.as_str().map(str::to_string))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            },
+            "status" => match content["execution_state"].as_str() {
+                Some("busy") => KernelMessage::Busy,
+                Some("idle") => KernelMessage::Idle,
+                _ => return None,
+            },
+            _ => return None,
+        })
+    }
+}
+
+/// Owns the kernel registry and the current buffer's language, and delivers
+/// execution replies back to the single `EditView` in the window. Mirrors
+/// `crate::App`'s own `sink`/`edit_view_id` pair, since this is effectively
+/// a second backend feeding the same widget.
+#[derive(Clone)]
+pub struct KernelManager {
+    registry: Arc<Mutex<KernelRegistry>>,
+    language: Arc<Mutex<Option<String>>>,
+    sink: Arc<Mutex<Option<ExtEventSink>>>,
+    edit_view_id: WidgetId,
+}
+
+impl KernelManager {
+    pub fn new(edit_view_id: WidgetId) -> KernelManager {
+        KernelManager {
+            registry: Default::default(),
+            language: Default::default(),
+            sink: Default::default(),
+            edit_view_id,
+        }
+    }
+
+    pub fn set_sink(&self, sink: ExtEventSink) {
+        *self.sink.lock().unwrap() = Some(sink);
+    }
+
+    pub fn set_language(&self, language: Option<String>) {
+        *self.language.lock().unwrap() = language;
+    }
+
+    pub fn register_known_languages(&self, languages: &[String]) {
+        self.registry.lock().unwrap().register_known(languages);
+    }
+
+    /// Spawns the kernel for the buffer's current language, feeds it `code`
+    /// as a single `execute_request`, and streams replies back to
+    /// `anchor_line`'s output block as they arrive. Does nothing if the
+    /// language isn't set or has no known kernel.
+    pub fn execute(&self, anchor_line: usize, code: String) {
+        let language = match self.language.lock().unwrap().clone() {
+            Some(language) => language,
+            None => return,
+        };
+        let spec = match self.registry.lock().unwrap().get(&language).cloned() {
+            Some(spec) => spec,
+            None => return,
+        };
+
+        let sink = self.sink.clone();
+        let edit_view_id = self.edit_view_id;
+
+        std::thread::spawn(move || {
+            let post = |message: KernelMessage| {
+                if let Some(sink) = sink.lock().unwrap().as_ref() {
+                    let cmd = EditViewCommands::KernelMessage {
+                        anchor_line,
+                        message,
+                    };
+                    let _ =
+                        sink.submit_command(EDIT_VIEW_COMMAND, cmd, Target::Widget(edit_view_id));
+                }
+            };
+
+            let mut child = match Command::new(&spec.cmd)
+                .args(&spec.args)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()
+            {
+                Ok(child) => child,
+                Err(e) => {
+                    post(KernelMessage::Error {
+                        ename: "KernelStartError".to_string(),
+                        evalue: e.to_string(),
+                        traceback: Vec::new(),
+                    });
+                    return;
+                }
+            };
+
+            if let Some(mut stdin) = child.stdin.take() {
+                let request = serde_json::json!({ "msg_type": "execute_request", "content": { "code": code } });
+                let _ = writeln!(stdin, "{}", request);
+            }
+
+            if let Some(stdout) = child.stdout.take() {
+                for line in BufReader::new(stdout).lines().flatten() {
+                    if let Ok(value) = serde_json::from_str::<Value>(&line) {
+                        if let Some(message) = KernelMessage::from_json(&value) {
+                            post(message);
+                        }
+                    }
+                }
+            }
+
+            let _ = child.wait();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn from_json_parses_stream() {
+        let line = json!({ "msg_type": "stream", "content": { "text": "hi\n" } });
+        match KernelMessage::from_json(&line) {
+            Some(KernelMessage::Stream { text }) => assert_eq!(text, "hi\n"),
+            other => panic!("expected Stream, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn from_json_parses_busy_and_idle_status() {
+        let busy = json!({ "msg_type": "status", "content": { "execution_state": "busy" } });
+        let idle = json!({ "msg_type": "status", "content": { "execution_state": "idle" } });
+        assert!(matches!(
+            KernelMessage::from_json(&busy),
+            Some(KernelMessage::Busy)
+        ));
+        assert!(matches!(
+            KernelMessage::from_json(&idle),
+            Some(KernelMessage::Idle)
+        ));
+    }
+
+    #[test]
+    fn from_json_returns_none_for_unrecognized_status() {
+        let line = json!({ "msg_type": "status", "content": { "execution_state": "starting" } });
+        assert!(KernelMessage::from_json(&line).is_none());
+    }
+
+    #[test]
+    fn from_json_returns_none_for_unknown_msg_type() {
+        let line = json!({ "msg_type": "comm_open", "content": {} });
+        assert!(KernelMessage::from_json(&line).is_none());
+    }
+
+    #[test]
+    fn from_json_parses_error_with_traceback() {
+        let line = json!({
+            "msg_type": "error",
+            "content": {
+                "ename": "ValueError",
+                "evalue": "boom",
+                "traceback": ["line 1", "line 2"],
+            }
+        });
+        match KernelMessage::from_json(&line) {
+            Some(KernelMessage::Error {
+                ename,
+                evalue,
+                traceback,
+            }) => {
+                assert_eq!(ename, "ValueError");
+                assert_eq!(evalue, "boom");
+                assert_eq!(traceback, vec!["line 1".to_string(), "line 2".to_string()]);
+            }
+            other => panic!("expected Error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn mime_bundle_decodes_base64_image() {
+        let encoded = base64::engine::general_purpose::STANDARD.encode([1u8, 2, 3]);
+        let line = json!({
+            "msg_type": "execute_result",
+            "content": { "data": { "image/png": encoded } }
+        });
+        match KernelMessage::from_json(&line) {
+            Some(KernelMessage::ExecuteResult(bundle)) => {
+                assert_eq!(bundle.image_png, Some(vec![1, 2, 3]));
+            }
+            other => panic!("expected ExecuteResult, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//! A local cache of the lines xi-core has sent us, kept in sync by replaying
+//! the `ops` list that arrives with every `update` notification.
+//!
+//! This mirrors the line cache used by other xi-editor front ends: xi-core
+//! never sends a full document, only a diff against what it believes we
+//! already have cached, so the front end must apply the same transform xi-core
+//! applied on its side to stay in sync.
+
+use serde_json::Value;
+
+/// A single contiguous run of one style applied to a line's text.
+///
+/// `start`/`length` are UTF-8 byte offsets into the line's text, and
+/// `style_id` indexes into the style map built up from `theme_changed`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyleSpan {
+    pub start: usize,
+    pub length: usize,
+    pub style_id: usize,
+}
+
+/// One line of text as rendered by the editor, including cursor positions
+/// and style spans local to this line.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Line {
+    pub text: String,
+    /// Byte offsets of cursors that fall on this line.
+    pub cursors: Vec<usize>,
+    pub styles: Vec<StyleSpan>,
+}
+
+impl Line {
+    fn from_json(value: &Value) -> Line {
+        let text = value["text"].as_str().unwrap_or_default().to_string();
+        let cursors = value["cursor"]
+            .as_array()
+            .map(|cursor| {
+                cursor
+                    .iter()
+                    .filter_map(|offset| offset.as_u64().map(|n| n as usize))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let styles = value["styles"]
+            .as_array()
+            .map(|raw| decode_styles(raw))
+            .unwrap_or_default();
+        Line {
+            text,
+            cursors,
+            styles,
+        }
+    }
+}
+
+/// xi-core packs style spans as a flat `[delta_start, length, style_id, ...]`
+/// triple list, where `delta_start` is relative to the end of the previous
+/// span (or the start of the line for the first one).
+fn decode_styles(raw: &[Value]) -> Vec<StyleSpan> {
+    let mut spans = Vec::with_capacity(raw.len() / 3);
+    let mut pos: i64 = 0;
+    for triple in raw.chunks(3) {
+        if triple.len() < 3 {
+            break;
+        }
+        let delta_start = triple[0].as_i64().unwrap_or(0);
+        let length = triple[1].as_i64().unwrap_or(0).max(0) as usize;
+        let style_id = triple[2].as_i64().unwrap_or(0).max(0) as usize;
+        pos += delta_start;
+        let start = pos.max(0) as usize;
+        pos += length as i64;
+        spans.push(StyleSpan {
+            start,
+            length,
+            style_id,
+        });
+    }
+    spans
+}
+
+/// The set of lines the `EditView` has fetched from xi-core, plus enough
+/// bookkeeping to know the total document height even for lines we haven't
+/// been sent text for yet.
+#[derive(Debug, Clone, Default)]
+pub struct LineCache {
+    lines: Vec<Option<Line>>,
+}
+
+impl LineCache {
+    pub fn new() -> LineCache {
+        LineCache::default()
+    }
+
+    /// Total number of lines in the document, including ones that are
+    /// currently invalidated (not yet fetched).
+    pub fn height(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// The line at `ix`, or `None` if it hasn't been fetched yet (it must be
+    /// requested lazily, e.g. by scrolling it into view).
+    pub fn get(&self, ix: usize) -> Option<&Line> {
+        self.lines.get(ix).and_then(|line| line.as_ref())
+    }
+
+    /// Returns `true` if every line in `range` is present in the cache.
+    pub fn is_valid_range(&self, start: usize, end: usize) -> bool {
+        (start..end.min(self.lines.len())).all(|ix| self.lines[ix].is_some())
+    }
+
+    /// Replays the `ops` array of an `update` RPC, transforming the previous
+    /// cache into the new one.
+    pub fn apply_update(&mut self, update: &Value) {
+        let ops = match update["ops"].as_array() {
+            Some(ops) => ops,
+            None => return,
+        };
+
+        let mut new_lines = Vec::with_capacity(self.lines.len());
+        let mut old_ix = 0usize;
+
+        for op in ops {
+            let op_type = op["op"].as_str().unwrap_or("");
+            let n = op["n"].as_u64().unwrap_or(0) as usize;
+
+            match op_type {
+                "copy" => {
+                    for _ in 0..n {
+                        new_lines.push(self.lines.get(old_ix).cloned().flatten());
+                        old_ix += 1;
+                    }
+                }
+                "skip" => {
+                    old_ix += n;
+                }
+                "invalidate" => {
+                    new_lines.resize(new_lines.len() + n, None);
+                }
+                "ins" => {
+                    if let Some(lines) = op["lines"].as_array() {
+                        new_lines.extend(lines.iter().map(|l| Some(Line::from_json(l))));
+                    }
+                }
+                "update" => {
+                    if let Some(lines) = op["lines"].as_array() {
+                        new_lines.extend(lines.iter().map(|l| Some(Line::from_json(l))));
+                    }
+                    old_ix += n;
+                }
+                other => warn!("unknown line cache op {}", other),
+            }
+        }
+
+        self.lines = new_lines;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn copy_and_invalidate_preserve_height() {
+        let mut cache = LineCache::new();
+        cache.apply_update(&json!({
+            "ops": [
+                { "op": "ins", "n": 2, "lines": [
+                    { "text": "one", "cursor": [], "styles": [] },
+                    { "text": "two", "cursor": [], "styles": [] },
+                ]},
+            ]
+        }));
+        assert_eq!(cache.height(), 2);
+        assert_eq!(cache.get(0).unwrap().text, "one");
+
+        cache.apply_update(&json!({
+            "ops": [
+                { "op": "copy", "n": 1 },
+                { "op": "invalidate", "n": 1 },
+            ]
+        }));
+        assert_eq!(cache.height(), 2);
+        assert_eq!(cache.get(0).unwrap().text, "one");
+        assert!(cache.get(1).is_none());
+        assert!(!cache.is_valid_range(0, 2));
+    }
+
+    #[test]
+    fn decode_styles_applies_relative_offsets() {
+        let line = Line::from_json(&json!({
+            "text": "hello world",
+            "cursor": [5],
+            "styles": [0, 5, 1, 1, 5, 2],
+        }));
+        assert_eq!(line.cursors, vec![5]);
+        assert_eq!(
+            line.styles,
+            vec![
+                StyleSpan {
+                    start: 0,
+                    length: 5,
+                    style_id: 1
+                },
+                StyleSpan {
+                    start: 6,
+                    length: 5,
+                    style_id: 2
+                },
+            ]
+        );
+    }
+}
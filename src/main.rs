@@ -8,58 +8,40 @@ use std::collections::HashMap;
 use std::rc::Weak;
 use std::sync::{Arc, Mutex};
 
-use druid::{AppLauncher, Data, Lens, UnitPoint, WidgetExt, WindowDesc, AppDelegate, Target, Command, DelegateCtx, Handled, Selector};
-use druid::widget::{Flex, Label, TextBox};
+use druid::widget::{Either, Flex, SizedBox};
+use druid::{AppLauncher, Color, Data, ExtEventSink, Selector, WidgetExt, WidgetId, WindowDesc, AppDelegate, Target, Command, DelegateCtx, Handled};
 use druid::widget::prelude::*;
 use serde_json::Value;
 
+use crate::edit_view::{EditView, EDIT_VIEW_COMMAND};
+use crate::find::{build_search_bar, TOGGLE_FIND};
+use crate::kernel::KernelManager;
+use crate::menu::{make_menu, APP_VIEW_COMMAND};
 use crate::rpc::{Core, Handler};
+use crate::semantic::EmbeddingManager;
+use crate::semantic_search::{build_semantic_search_bar, TOGGLE_SEMANTIC_SEARCH};
 use crate::xi_thread::start_xi_thread;
 use std::thread;
 
 pub mod xi_thread;
 pub mod rpc;
-
-
-const VERTICAL_WIDGET_SPACING: f64 = 20.0;
-const TEXT_BOX_WIDTH: f64 = 200.0;
+pub mod line_cache;
+pub mod ansi;
+pub mod kernel;
+pub mod execution;
+pub mod semantic;
+pub mod semantic_store;
+pub mod semantic_search;
+pub mod edit_view;
+pub mod menu;
+pub mod theme;
+pub mod find;
 
 pub type Id = usize;
-
-#[derive(Clone, Data, Lens)]
-struct HelloState {
-    name: String,
-}
-
-fn build_root_widget() -> impl Widget<HelloState> {
-    // a label that will determine its text based on the current app data.
-    let label = Label::new(|data: &HelloState, _env: &Env| {
-        if data.name.is_empty() {
-            "Hello anybody!?".to_string()
-        } else {
-            format!("Hello {}!", data.name)
-        }
-    })
-        .with_text_size(32.0);
-
-    // a textbox that modifies `name`.
-    let textbox = TextBox::new()
-        .with_placeholder("Who are we greeting?")
-        .with_text_size(18.0)
-        .fix_width(TEXT_BOX_WIDTH)
-        .lens(HelloState::name);
-
-    // arrange the two widgets vertically, with some padding
-    Flex::column()
-        .with_child(label)
-        .with_spacer(VERTICAL_WIDGET_SPACING)
-        .with_child(textbox)
-        .align_vertical(UnitPoint::CENTER)
-}
-
-type ViewId = String;
+pub type ViewId = String;
 
 /// The commands the EditView widget accepts through `poke`.
+#[derive(Clone)]
 pub enum EditViewCommands {
     ViewId(String),
     ApplyUpdate(Value),
@@ -74,13 +56,114 @@ pub enum EditViewCommands {
     AddCursorBelow,
     SingleSelection,
     SelectAll,
+    /// Requests that xi-core switch the active theme; the resulting
+    /// `theme_changed` notification is what actually restyles the view.
+    SetTheme(String),
+    /// Pushes a freshly parsed theme's per-style-id color map into the
+    /// `EditView` so it can resolve `update` line spans against it.
+    ApplyTheme(theme::Theme),
+    Find {
+        query: String,
+        case_sensitive: bool,
+        regex: bool,
+        whole_words: bool,
+    },
+    FindNext,
+    FindPrevious,
+    Replace(String),
+    ReplaceNext,
+    ReplaceAll,
+    /// Runs the current line through the buffer's language kernel; see
+    /// `crate::kernel::KernelManager::execute`.
+    Execute,
+    /// One reply from a kernel run, delivered straight to the `EditView`
+    /// since it owns the `ExecutionStore` the block lives in.
+    KernelMessage {
+        anchor_line: usize,
+        message: kernel::KernelMessage,
+    },
+    /// Embeds the query and ranks the buffer's chunks by cosine similarity;
+    /// see `crate::semantic::EmbeddingManager::query`.
+    SemanticSearch(String),
 }
 
+/// One ranked chunk in the semantic search results list, rendered by
+/// `crate::semantic_search::build_semantic_search_bar`.
+#[derive(Clone, Data, PartialEq)]
+pub struct SemanticResultRow {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub score: f64,
+    pub snippet: String,
+}
 
-#[derive(Clone, Data)]
+/// Pushed from `App` into the root `ViewState` whenever xi-core reports a
+/// change that should be reflected in the menu (edit history, focus, theme).
+/// Each field is `None` when that aspect is unchanged.
+#[derive(Clone)]
+pub struct ViewStatus {
+    can_undo: Option<bool>,
+    can_redo: Option<bool>,
+    has_focus: Option<bool>,
+    available_themes: Option<druid::im::Vector<String>>,
+    theme_fg: Option<Color>,
+    theme_bg: Option<Color>,
+    theme_caret: Option<Color>,
+    theme_selection: Option<Color>,
+    find_matches: Option<usize>,
+    current_language: Option<String>,
+    semantic_results: Option<druid::im::Vector<SemanticResultRow>>,
+}
+
+impl ViewStatus {
+    fn none() -> ViewStatus {
+        ViewStatus {
+            can_undo: None,
+            can_redo: None,
+            has_focus: None,
+            available_themes: None,
+            theme_fg: None,
+            theme_bg: None,
+            theme_caret: None,
+            theme_selection: None,
+            find_matches: None,
+            current_language: None,
+            semantic_results: None,
+        }
+    }
+}
+
+/// Applied to the root `ViewState` by the `AppDelegate`, never sent directly
+/// to a widget.
+pub const UPDATE_VIEW_STATUS: Selector<ViewStatus> = Selector::new("druid-xi.update-view-status");
+
+
+#[derive(Clone, Data, druid::Lens)]
 struct ViewState {
     id: Id,
     filename: Option<String>,
+    widget_id: WidgetId,
+    /// Mirrors `AppState.focused.is_some()`, kept on the root `Data` so the
+    /// menu can disable every view command when nothing is focused.
+    has_focus: bool,
+    can_undo: bool,
+    can_redo: bool,
+    available_themes: druid::im::Vector<String>,
+    theme_fg: Option<Color>,
+    theme_bg: Option<Color>,
+    theme_caret: Option<Color>,
+    theme_selection: Option<Color>,
+    pub find_open: bool,
+    pub find_query: String,
+    pub find_case_sensitive: bool,
+    pub find_regex: bool,
+    pub find_whole_words: bool,
+    pub find_matches: usize,
+    pub find_replacement: String,
+    current_language: Option<String>,
+    pub semantic_open: bool,
+    pub semantic_query: String,
+    pub semantic_results: druid::im::Vector<SemanticResultRow>,
 }
 
 #[derive(Clone)]
@@ -97,13 +180,18 @@ impl AppState {
         }
     }
 
-    fn get_focused(&self) -> String {
-        self.focused.clone().expect("no focused viewstate")
+    /// `None` before the first `new_view` response has landed (or, in
+    /// principle, between a view closing and another gaining focus) — every
+    /// caller needs to treat "not focused yet" as routine, not exceptional,
+    /// since ordinary early input (typing in the find bar before the app has
+    /// finished starting up) can reach these before any view exists.
+    fn get_focused(&self) -> Option<String> {
+        self.focused.clone()
     }
 
-    fn get_focused_viewstate(&mut self) -> &mut ViewState {
-        let view_id = self.focused.clone().expect("no focused viewstate");
-        self.views.get_mut(&view_id).expect("Focused viewstate not found in views")
+    fn get_focused_viewstate(&mut self) -> Option<&mut ViewState> {
+        let view_id = self.focused.clone()?;
+        self.views.get_mut(&view_id)
     }
 }
 
@@ -111,23 +199,168 @@ impl AppState {
 struct App {
     core: Arc<Mutex<Core>>,
     state: Arc<Mutex<AppState>>,
+    sink: Arc<Mutex<Option<ExtEventSink>>>,
+    /// Tracks the buffer's current language and the kernels known for it;
+    /// kept alongside `core` since `available_languages`/`language_changed`
+    /// arrive over the same xi-core connection.
+    kernels: Arc<Mutex<KernelManager>>,
+    /// The semantic search index; fed the same `update` payloads as the
+    /// focused `EditView`'s own line cache.
+    embeddings: Arc<Mutex<EmbeddingManager>>,
+    /// `WidgetId` of the single `EditView` currently in the window. Once this
+    /// app supports multiple tabs/panes this will move into the new-view
+    /// response instead of being fixed at startup.
+    edit_view_id: WidgetId,
 }
 
 impl App {
-    fn new(core: Core) -> App {
+    fn new(
+        core: Arc<Mutex<Core>>,
+        kernels: Arc<Mutex<KernelManager>>,
+        embeddings: Arc<Mutex<EmbeddingManager>>,
+        edit_view_id: WidgetId,
+    ) -> App {
         App {
-            core: Arc::new(Mutex::new(core)),
+            core,
             state: Arc::new(Mutex::new(AppState::new())),
+            sink: Default::default(),
+            kernels,
+            embeddings,
+            edit_view_id,
         }
     }
 
+    fn set_sink(&self, sink: ExtEventSink) {
+        *self.sink.lock().unwrap() = Some(sink);
+    }
+
     fn send_notification(&self, method: &str, params: &Value) {
         self.get_core().send_notification(method, params);
     }
 
+    /// Sends a notification scoped to the focused view, injecting its
+    /// `view_id` the same way `EditView::send_notification` does for edits
+    /// that originate inside the widget itself. Dropped (with a log line)
+    /// if nothing is focused yet, rather than panicking on input that races
+    /// the `new_view` response.
+    fn send_view_notification(&self, method: &str, mut params: Value) {
+        let view_id = match self.get_state().get_focused() {
+            Some(view_id) => view_id,
+            None => {
+                warn!("dropping \"{}\": no focused view yet", method);
+                return;
+            }
+        };
+        params["view_id"] = json!(view_id);
+        self.send_notification(method, &params);
+    }
+
+    /// Routes a command to the focused view's `EditView` widget, except for
+    /// the commands below, which aren't view-local edits but requests that
+    /// go straight to xi-core (theme switching, search) — the resulting
+    /// `theme_changed`/`update`/`find_status` notifications are what
+    /// actually update the view. `SemanticSearch` is the one exception that
+    /// goes to the embedding index instead of xi-core.
     fn send_view_cmd(&self, cmd: EditViewCommands) {
+        match cmd {
+            EditViewCommands::SetTheme(name) => {
+                self.send_notification("set_theme", &json!({ "theme_name": name }));
+                return;
+            }
+            EditViewCommands::Undo => {
+                self.send_view_notification("undo", json!({}));
+                return;
+            }
+            EditViewCommands::Redo => {
+                self.send_view_notification("redo", json!({}));
+                return;
+            }
+            EditViewCommands::Transpose => {
+                self.send_view_notification("transpose", json!({}));
+                return;
+            }
+            EditViewCommands::UpperCase => {
+                self.send_view_notification("uppercase", json!({}));
+                return;
+            }
+            EditViewCommands::LowerCase => {
+                self.send_view_notification("lowercase", json!({}));
+                return;
+            }
+            EditViewCommands::SelectAll => {
+                self.send_view_notification("select_all", json!({}));
+                return;
+            }
+            EditViewCommands::SingleSelection => {
+                self.send_view_notification("collapse_selections", json!({}));
+                return;
+            }
+            EditViewCommands::AddCursorAbove => {
+                self.send_view_notification("add_selection_above", json!({}));
+                return;
+            }
+            EditViewCommands::AddCursorBelow => {
+                self.send_view_notification("add_selection_below", json!({}));
+                return;
+            }
+            EditViewCommands::Find { query, case_sensitive, regex, whole_words } => {
+                self.send_view_notification("find", json!({
+                    "id": 0,
+                    "chars": query,
+                    "case_sensitive": case_sensitive,
+                    "regex": regex,
+                    "whole_words": whole_words,
+                }));
+                return;
+            }
+            EditViewCommands::FindNext => {
+                self.send_view_notification("find_next", json!({}));
+                return;
+            }
+            EditViewCommands::FindPrevious => {
+                self.send_view_notification("find_previous", json!({}));
+                return;
+            }
+            EditViewCommands::Replace(ref chars) => {
+                self.send_view_notification("replace", json!({ "chars": chars }));
+                return;
+            }
+            EditViewCommands::ReplaceNext => {
+                self.send_view_notification("replace_next", json!({}));
+                return;
+            }
+            EditViewCommands::ReplaceAll => {
+                self.send_view_notification("replace_all", json!({}));
+                return;
+            }
+            EditViewCommands::SemanticSearch(query) => {
+                let doc_id = match self.semantic_doc_id() {
+                    Some(doc_id) => doc_id,
+                    None => {
+                        warn!("dropping semantic search: no focused view yet");
+                        return;
+                    }
+                };
+                self.embeddings.lock().unwrap().query(&doc_id, &query, 5);
+                return;
+            }
+            _ => (),
+        }
+
         let mut state = self.get_state();
-        let focused = state.get_focused_viewstate();
+        let focused = match state.get_focused_viewstate() {
+            Some(focused) => focused,
+            None => {
+                warn!("dropping view command: no focused view yet");
+                return;
+            }
+        };
+        let target = Target::Widget(focused.widget_id);
+        if let Some(sink) = self.sink.lock().unwrap().as_ref() {
+            if let Err(e) = sink.submit_command(EDIT_VIEW_COMMAND, cmd, target) {
+                warn!("failed to deliver view command: {}", e);
+            }
+        }
     }
 }
 
@@ -139,6 +372,19 @@ impl App {
     fn get_state(&self) -> std::sync::MutexGuard<'_, AppState, > {
         self.state.lock().unwrap()
     }
+
+    /// The stable id `EmbeddingManager` persists chunks under for the
+    /// focused view: its filename if it has one, since xi-core hands out a
+    /// fresh `ViewId` every session and keying the cache by that would mean
+    /// nothing ever survives a restart. Falls back to the `ViewId` itself
+    /// for a buffer that hasn't been saved yet, or `None` if nothing is
+    /// focused yet.
+    fn semantic_doc_id(&self) -> Option<String> {
+        let mut state = self.get_state();
+        let view_id = state.get_focused()?;
+        let filename = state.get_focused_viewstate()?.filename.clone();
+        Some(filename.unwrap_or(view_id))
+    }
 }
 
 impl App {
@@ -152,32 +398,160 @@ impl App {
             None
         };
 
-        let edit_view = 0;
-        let core = Arc::downgrade(&self.core);
         let state = self.state.clone();
+        let edit_view_id = self.edit_view_id;
+        let app = self.clone();
 
         self.core.lock().unwrap()
             .send_request("new_view", &params,
                           move |value| {
                               let view_id = value.clone().as_str().unwrap().to_string();
                               let mut state = state.lock().unwrap();
+                              state.views.insert(view_id.clone(), ViewState {
+                                  id: state.views.len(),
+                                  filename: filename.clone(),
+                                  widget_id: edit_view_id,
+                                  has_focus: true,
+                                  can_undo: false,
+                                  can_redo: false,
+                                  available_themes: Default::default(),
+                                  theme_fg: None,
+                                  theme_bg: None,
+                                  theme_caret: None,
+                                  theme_selection: None,
+                                  find_open: false,
+                                  find_query: String::new(),
+                                  find_case_sensitive: false,
+                                  find_regex: false,
+                                  find_whole_words: false,
+                                  find_matches: 0,
+                                  find_replacement: String::new(),
+                                  current_language: None,
+                                  semantic_open: false,
+                                  semantic_query: String::new(),
+                                  semantic_results: Default::default(),
+                              });
                               state.focused = Some(view_id.clone());
+                              drop(state);
+                              app.send_view_cmd(EditViewCommands::ViewId(view_id));
+                              app.update_view_status(ViewStatus {
+                                  has_focus: Some(true),
+                                  ..ViewStatus::none()
+                              });
                           },
             );
     }
 
+    /// Pushes a change onto the root `ViewState` so the menu picks up new
+    /// enabled flags; handled by the `Delegate`, never sent to a widget.
+    fn update_view_status(&self, status: ViewStatus) {
+        if let Some(sink) = self.sink.lock().unwrap().as_ref() {
+            if let Err(e) = sink.submit_command(UPDATE_VIEW_STATUS, status, Target::Global) {
+                warn!("failed to push view status: {}", e);
+            }
+        }
+    }
+
     fn handle_cmd(&self, method: &str, params: &Value) {
         match method {
-            "update" => (),
-            "scroll_to" => (),
-            "available_themes" => (), // TODO
+            "update" => {
+                self.forward_to_focused_view(EditViewCommands::ApplyUpdate(params.clone()));
+                // xi-core folds edit-history availability into the same
+                // payload as the line cache diff when it's present.
+                self.update_view_status(ViewStatus {
+                    can_undo: params["undo_available"].as_bool(),
+                    can_redo: params["redo_available"].as_bool(),
+                    ..ViewStatus::none()
+                });
+                let focused = self.get_state().focused.clone();
+                if let Some(focused) = focused {
+                    if let Some(doc_id) = self.semantic_doc_id() {
+                        self.embeddings
+                            .lock()
+                            .unwrap()
+                            .apply_update(&focused, &doc_id, params);
+                    }
+                }
+            }
+            "scroll_to" => {
+                if let Some(line) = params["line"].as_u64() {
+                    self.forward_to_focused_view(EditViewCommands::ScrollTo(line as usize));
+                }
+            }
+            "available_themes" => {
+                if let Some(themes) = params["themes"].as_array() {
+                    let themes: druid::im::Vector<String> = themes
+                        .iter()
+                        .filter_map(|t| t.as_str().map(str::to_string))
+                        .collect();
+                    self.update_view_status(ViewStatus {
+                        available_themes: Some(themes),
+                        ..ViewStatus::none()
+                    });
+                }
+            }
             "available_plugins" => (), // TODO
-            "available_languages" => (), // TODO
-            "config_changed" => (), // TODO
-            "language_changed" => (), // TODO
+            "available_languages" => {
+                if let Some(languages) = params["languages"].as_array() {
+                    let languages: Vec<String> = languages
+                        .iter()
+                        .filter_map(|l| l.as_str().map(str::to_string))
+                        .collect();
+                    self.kernels.lock().unwrap().register_known_languages(&languages);
+                }
+            }
+            "config_changed" => {
+                self.update_view_status(ViewStatus {
+                    can_undo: params["changes"]["undo_available"].as_bool(),
+                    can_redo: params["changes"]["redo_available"].as_bool(),
+                    ..ViewStatus::none()
+                });
+            }
+            "theme_changed" => {
+                let name = params["name"].as_str().unwrap_or_default();
+                let theme = theme::Theme::from_json(name, &params["theme"]);
+                self.update_view_status(ViewStatus {
+                    theme_fg: Some(theme.foreground.clone()),
+                    theme_bg: Some(theme.background.clone()),
+                    theme_caret: Some(theme.caret.clone()),
+                    theme_selection: Some(theme.selection.clone()),
+                    ..ViewStatus::none()
+                });
+                self.forward_to_focused_view(EditViewCommands::ApplyTheme(theme));
+            }
+            "find_status" => {
+                // One status object per active query id; this app only ever
+                // runs a single query at a time, so just look at the first.
+                if let Some(matches) = params.as_array()
+                    .and_then(|queries| queries.get(0))
+                    .and_then(|status| status["matches"].as_u64())
+                {
+                    self.update_view_status(ViewStatus {
+                        find_matches: Some(matches as usize),
+                        ..ViewStatus::none()
+                    });
+                }
+            }
+            "language_changed" => {
+                let language = params["languageid"].as_str().map(str::to_string);
+                self.kernels.lock().unwrap().set_language(language.clone());
+                self.update_view_status(ViewStatus {
+                    current_language: language,
+                    ..ViewStatus::none()
+                });
+            }
             _ => println!("unhandled core->fe method {}", method),
         }
     }
+
+    /// `update`/`scroll_to` notifications carry a `view_id` but, since this
+    /// app only ever has one view focused at a time, we route them straight
+    /// to whichever `EditView` is currently focused.
+    fn forward_to_focused_view(&self, cmd: EditViewCommands) {
+        if self.get_state().focused.is_some() {
+            self.send_view_cmd(cmd);
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -195,10 +569,6 @@ impl AppDispatcher {
     fn set_app(&self, app: &App) {
         *self.app.lock().unwrap() = Some(app.clone());
     }
-
-    fn set_menu_listeners(&self) {
-        let app = self.app.clone();
-    }
 }
 
 
@@ -210,12 +580,78 @@ impl Handler for AppDispatcher {
     }
 }
 
-#[derive(Debug, Default)]
-pub struct Delegate;
+pub struct Delegate {
+    app: App,
+}
+
+impl Delegate {
+    fn new(app: App) -> Delegate {
+        Delegate { app }
+    }
+}
 
 impl AppDelegate<ViewState> for Delegate {
-    fn command(&mut self, ctx: &mut DelegateCtx, target: Target, cmd: &Command, data: &mut ViewState, env: &Env) -> Handled {
-        Handled::Yes
+    fn command(
+        &mut self,
+        _ctx: &mut DelegateCtx,
+        _target: Target,
+        cmd: &Command,
+        data: &mut ViewState,
+        _env: &Env,
+    ) -> Handled {
+        if let Some(status) = cmd.get(UPDATE_VIEW_STATUS) {
+            if let Some(can_undo) = status.can_undo {
+                data.can_undo = can_undo;
+            }
+            if let Some(can_redo) = status.can_redo {
+                data.can_redo = can_redo;
+            }
+            if let Some(has_focus) = status.has_focus {
+                data.has_focus = has_focus;
+            }
+            if let Some(themes) = status.available_themes {
+                data.available_themes = themes;
+            }
+            if status.theme_fg.is_some() {
+                data.theme_fg = status.theme_fg;
+            }
+            if status.theme_bg.is_some() {
+                data.theme_bg = status.theme_bg;
+            }
+            if status.theme_caret.is_some() {
+                data.theme_caret = status.theme_caret;
+            }
+            if status.theme_selection.is_some() {
+                data.theme_selection = status.theme_selection;
+            }
+            if let Some(find_matches) = status.find_matches {
+                data.find_matches = find_matches;
+            }
+            if status.current_language.is_some() {
+                data.current_language = status.current_language;
+            }
+            if let Some(results) = status.semantic_results {
+                data.semantic_results = results;
+            }
+            return Handled::Yes;
+        }
+
+        if let Some(view_cmd) = cmd.get(APP_VIEW_COMMAND) {
+            self.app.send_view_cmd(view_cmd.clone());
+            return Handled::Yes;
+        }
+
+        if cmd.get(TOGGLE_FIND).is_some() {
+            data.find_open = !data.find_open;
+            return Handled::Yes;
+        }
+
+        if cmd.get(TOGGLE_SEMANTIC_SEARCH).is_some() {
+            data.semantic_open = !data.semantic_open;
+            return Handled::Yes;
+        }
+
+        Handled::No
     }
 }
 
@@ -224,34 +660,86 @@ pub fn main() {
 
     let (xi_peer, rx) = start_xi_thread();
 
-    let main_window = WindowDesc::new(build_root_widget())
-        .title("Hello World!")
-        .window_size((400.0, 400.0));
-
-    let initial_state: HelloState = HelloState {
-        name: "World".into(),
-    };
-
     let handler = AppDispatcher::new();
-    handler.set_menu_listeners();
-
-    let core = Core::new(xi_peer, rx, handler.clone());
-    let app = App::new(core);
 
+    let core = Arc::new(Mutex::new(Core::new(xi_peer, rx, handler.clone())));
+    let core_weak = Arc::downgrade(&core);
+
+    let edit_view_id = WidgetId::next();
+    let kernels = Arc::new(Mutex::new(KernelManager::new(edit_view_id)));
+    let kernels_weak = Arc::downgrade(&kernels);
+    let embeddings = Arc::new(Mutex::new(EmbeddingManager::new(
+        edit_view_id,
+        "http://localhost:8000/embed".to_string(),
+        "druid-xi-embeddings.sqlite3",
+    )));
+    let app = App::new(core, kernels.clone(), embeddings.clone(), edit_view_id);
+
+    let root = Flex::column()
+        .with_child(Either::new(
+            |data: &ViewState, _| data.find_open,
+            build_search_bar(),
+            SizedBox::empty(),
+        ))
+        .with_child(Either::new(
+            |data: &ViewState, _| data.semantic_open,
+            build_semantic_search_bar(),
+            SizedBox::empty(),
+        ))
+        .with_flex_child(
+            EditView::new(String::new(), core_weak, kernels_weak).with_id(edit_view_id),
+            1.0,
+        );
+
+    let main_window = WindowDesc::new(root)
+        .title("xi-editor")
+        .window_size((800.0, 600.0))
+        .menu(make_menu);
+
+    let initial_state = ViewState {
+        id: 0,
+        filename: None,
+        widget_id: edit_view_id,
+        has_focus: false,
+        can_undo: false,
+        can_redo: false,
+        available_themes: Default::default(),
+        theme_fg: None,
+        theme_bg: None,
+        theme_caret: None,
+        theme_selection: None,
+        find_open: false,
+        find_query: String::new(),
+        find_case_sensitive: false,
+        find_regex: false,
+        find_whole_words: false,
+        find_matches: 0,
+        find_replacement: String::new(),
+        current_language: None,
+        semantic_open: false,
+        semantic_query: String::new(),
+        semantic_results: Default::default(),
+    };
 
-    let launcher = AppLauncher::with_window(main_window);
-    let handler = launcher.get_external_handle();
+    let launcher = AppLauncher::with_window(main_window)
+        .delegate(Delegate::new(app.clone()))
+        .configure_env(|env, data: &ViewState| {
+            let defaults = theme::Theme::default();
+            env.set(theme::EDITOR_FOREGROUND, data.theme_fg.clone().unwrap_or(defaults.foreground));
+            env.set(theme::EDITOR_BACKGROUND, data.theme_bg.clone().unwrap_or(defaults.background));
+            env.set(theme::EDITOR_CARET, data.theme_caret.clone().unwrap_or(defaults.caret));
+            env.set(theme::EDITOR_SELECTION, data.theme_selection.clone().unwrap_or(defaults.selection));
+        });
+    app.set_sink(launcher.get_external_handle());
+    kernels.lock().unwrap().set_sink(launcher.get_external_handle());
+    embeddings.lock().unwrap().set_sink(launcher.get_external_handle());
+
+    handler.set_app(&app);
 
     app.send_notification("client_started", &json!({}));
     app.req_new_view(None);
     app.send_notification("set_theme", &json!({ "theme_name": "InspiredGitHub" }));
 
-    let _thread = thread::spawn(move || {
-        handler
-            .submit_command(Selector::<()>::new("Test"), Box::new(()), Target::Auto)
-            .expect("Failed to send command");
-    });
-
     launcher
         .launch(initial_state)
         .expect("Failed to launch application");
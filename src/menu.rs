@@ -0,0 +1,173 @@
+//! The native application menu. Every item except the two search-bar
+//! toggles ("Find…", "Search by Meaning…") dispatches an `EditViewCommands`
+//! variant back to the focused view through [`crate::App::send_view_cmd`];
+//! the menu itself only decides *labels*, *keystrokes* and *enabled state* —
+//! it never touches xi-core directly.
+
+use druid::{Env, LocalizedString, Menu, MenuItem, SysMods, WindowId};
+
+use crate::find::TOGGLE_FIND;
+use crate::semantic_search::TOGGLE_SEMANTIC_SEARCH;
+use crate::{EditViewCommands, ViewState};
+
+/// Posted by chrome (menu items, the search bar) rather than
+/// [`crate::edit_view::EDIT_VIEW_COMMAND`] directly, so the `AppDelegate` can
+/// route it to the currently focused view through `App::send_view_cmd`
+/// instead of every caller having to know which widget that is.
+pub const APP_VIEW_COMMAND: druid::Selector<EditViewCommands> =
+    druid::Selector::new("druid-xi.app-view-command");
+
+/// Builds the menu bar from the root `ViewState`, so it is rebuilt with
+/// up-to-date keystrokes and enabled flags whenever that state changes
+/// (most importantly, whenever the focused view or its undo/redo
+/// availability changes).
+pub fn make_menu(_window: Option<WindowId>, state: &ViewState, _env: &Env) -> Menu<ViewState> {
+    Menu::empty()
+        .entry(edit_menu(state))
+        .entry(selection_menu(state))
+        .entry(find_menu(state))
+        .entry(theme_menu(state))
+        .entry(run_menu(state))
+}
+
+/// Only enabled once `language_changed` has told us what kernel to run the
+/// buffer through; see `crate::kernel::KernelRegistry`.
+fn run_menu(state: &ViewState) -> Menu<ViewState> {
+    Menu::new(LocalizedString::new("menu-run-menu").with_placeholder("Run")).entry(
+        view_cmd_item(
+            "Run Line",
+            EditViewCommands::Execute,
+            state.has_focus && state.current_language.is_some(),
+        )
+        .hotkey(SysMods::Cmd, "Return"),
+    )
+}
+
+fn find_menu(state: &ViewState) -> Menu<ViewState> {
+    Menu::new(LocalizedString::new("menu-find-menu").with_placeholder("Find"))
+        .entry(
+            MenuItem::new(LocalizedString::new("menu-find").with_placeholder("Find…"))
+                .command(TOGGLE_FIND.with(()))
+                .hotkey(SysMods::Cmd, "f")
+                .enabled(state.has_focus),
+        )
+        .entry(view_cmd_item(
+            "Find Next",
+            EditViewCommands::FindNext,
+            state.has_focus,
+        ))
+        .entry(view_cmd_item(
+            "Find Previous",
+            EditViewCommands::FindPrevious,
+            state.has_focus,
+        ))
+        .separator()
+        .entry(view_cmd_item(
+            "Replace",
+            EditViewCommands::Replace(state.find_replacement.clone()),
+            state.has_focus,
+        ))
+        .entry(view_cmd_item(
+            "Replace Next",
+            EditViewCommands::ReplaceNext,
+            state.has_focus,
+        ))
+        .entry(view_cmd_item(
+            "Replace All",
+            EditViewCommands::ReplaceAll,
+            state.has_focus,
+        ))
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-semantic-search").with_placeholder("Search by Meaning…"),
+            )
+            .command(TOGGLE_SEMANTIC_SEARCH.with(()))
+            .hotkey(SysMods::CmdShift, "f")
+            .enabled(state.has_focus),
+        )
+}
+
+/// Populated from the `themes` list xi-core sends with `available_themes`;
+/// empty until that notification arrives.
+fn theme_menu(state: &ViewState) -> Menu<ViewState> {
+    let mut menu = Menu::new(LocalizedString::new("menu-theme-menu").with_placeholder("Theme"));
+    for name in state.available_themes.iter() {
+        menu = menu.entry(
+            MenuItem::new(LocalizedString::new("theme-item").with_placeholder(name.as_str()))
+                .command(APP_VIEW_COMMAND.with(EditViewCommands::SetTheme(name.clone()))),
+        );
+    }
+    menu
+}
+
+fn edit_menu(state: &ViewState) -> Menu<ViewState> {
+    Menu::new(LocalizedString::new("menu-edit-menu").with_placeholder("Edit"))
+        .entry(
+            view_cmd_item(
+                "Undo",
+                EditViewCommands::Undo,
+                state.has_focus && state.can_undo,
+            )
+            .hotkey(SysMods::Cmd, "z"),
+        )
+        .entry(
+            view_cmd_item(
+                "Redo",
+                EditViewCommands::Redo,
+                state.has_focus && state.can_redo,
+            )
+            .hotkey(SysMods::CmdShift, "Z"),
+        )
+        .separator()
+        .entry(view_cmd_item(
+            "Transpose",
+            EditViewCommands::Transpose,
+            state.has_focus,
+        ))
+        .entry(view_cmd_item(
+            "Make Uppercase",
+            EditViewCommands::UpperCase,
+            state.has_focus,
+        ))
+        .entry(view_cmd_item(
+            "Make Lowercase",
+            EditViewCommands::LowerCase,
+            state.has_focus,
+        ))
+}
+
+fn selection_menu(state: &ViewState) -> Menu<ViewState> {
+    Menu::new(LocalizedString::new("menu-selection-menu").with_placeholder("Selection"))
+        .entry(
+            view_cmd_item("Select All", EditViewCommands::SelectAll, state.has_focus)
+                .hotkey(SysMods::Cmd, "a"),
+        )
+        .entry(view_cmd_item(
+            "Collapse to Single Selection",
+            EditViewCommands::SingleSelection,
+            state.has_focus,
+        ))
+        .separator()
+        .entry(
+            view_cmd_item(
+                "Add Cursor Above",
+                EditViewCommands::AddCursorAbove,
+                state.has_focus,
+            )
+            .hotkey(SysMods::CmdShift, "ArrowUp"),
+        )
+        .entry(
+            view_cmd_item(
+                "Add Cursor Below",
+                EditViewCommands::AddCursorBelow,
+                state.has_focus,
+            )
+            .hotkey(SysMods::CmdShift, "ArrowDown"),
+        )
+}
+
+fn view_cmd_item(title: &'static str, cmd: EditViewCommands, enabled: bool) -> MenuItem<ViewState> {
+    MenuItem::new(LocalizedString::new(title).with_placeholder(title))
+        .command(APP_VIEW_COMMAND.with(cmd))
+        .enabled(enabled)
+}
@@ -0,0 +1,543 @@
+//! Natural-language search over the open buffer, built on vector embeddings
+//! rather than literal text matching (see [`crate::find`] for that).
+//!
+//! Each buffer is split into overlapping line-range chunks; a chunk's
+//! embedding is computed by [`EmbeddingClient`] and kept in an in-memory
+//! [`SemanticIndex`], mirrored to a [`crate::semantic_store`] SQLite cache so
+//! unchanged chunks don't need to hit the embedding endpoint again after a
+//! restart. [`EmbeddingManager`] ties these together the same way
+//! [`crate::kernel::KernelManager`] ties together kernels: it owns the
+//! shared state and delivers results back to the focused `EditView` through
+//! its `ExtEventSink`.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
+use druid::{im, ExtEventSink, Target, WidgetId};
+use serde_json::{json, Value};
+
+use crate::edit_view::EDIT_VIEW_COMMAND;
+use crate::line_cache::LineCache;
+use crate::{EditViewCommands, SemanticResultRow, ViewId, ViewStatus};
+
+/// Lines per chunk and the overlap between consecutive chunks, so a match
+/// that straddles a chunk boundary still surfaces in one of them.
+const CHUNK_LINES: usize = 40;
+const CHUNK_OVERLAP: usize = 5;
+
+/// One chunk of source text, ready to embed or already embedded.
+#[derive(Debug, Clone)]
+struct Chunk {
+    start_line: usize,
+    end_line: usize,
+    text: String,
+}
+
+/// Splits `lines` into overlapping windows of `CHUNK_LINES`, skipping any
+/// window that contains a line we haven't fetched from xi-core yet.
+fn chunk_lines(lines: &[Option<String>]) -> Vec<Chunk> {
+    if lines.is_empty() {
+        return Vec::new();
+    }
+    let stride = CHUNK_LINES - CHUNK_OVERLAP;
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        let end = (start + CHUNK_LINES).min(lines.len());
+        let window = &lines[start..end];
+        if window.iter().all(Option::is_some) {
+            let text = window
+                .iter()
+                .map(|l| l.as_ref().unwrap().as_str())
+                .collect::<Vec<_>>()
+                .join("\n");
+            chunks.push(Chunk {
+                start_line: start,
+                end_line: end - 1,
+                text,
+            });
+        }
+        if end == lines.len() {
+            break;
+        }
+        start += stride;
+    }
+    chunks
+}
+
+fn text_hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// L2-normalizes `vector` in place so cosine similarity reduces to a dot
+/// product.
+fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        for v in vector.iter_mut() {
+            *v /= norm;
+        }
+    }
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+}
+
+/// Calls a configurable HTTP endpoint that embeds a string into an `f32`
+/// vector: it POSTs `{"input": text}` and reads the vector from a top-level
+/// `embedding` array in the JSON reply.
+#[derive(Debug, Clone)]
+pub struct EmbeddingClient {
+    endpoint: String,
+}
+
+impl EmbeddingClient {
+    pub fn new(endpoint: String) -> EmbeddingClient {
+        EmbeddingClient { endpoint }
+    }
+
+    /// Returns an L2-normalized embedding for `text`, or `None` if the
+    /// endpoint is unreachable or returns something we don't understand.
+    fn embed(&self, text: &str) -> Option<Vec<f32>> {
+        let response: Value = ureq::post(&self.endpoint)
+            .send_json(json!({ "input": text }))
+            .ok()?
+            .into_json()
+            .ok()?;
+        let mut vector: Vec<f32> = response["embedding"]
+            .as_array()?
+            .iter()
+            .map(|v| v.as_f64().unwrap_or(0.0) as f32)
+            .collect();
+        normalize(&mut vector);
+        Some(vector)
+    }
+}
+
+/// One chunk's embedding, plus enough to decide whether it's stale.
+#[derive(Debug, Clone)]
+struct IndexedChunk {
+    vector: Vec<f32>,
+    text_hash: u64,
+    snippet: String,
+}
+
+/// Identifies a chunk by the stable per-document id `EmbeddingManager`
+/// indexes under — the file path, not xi-core's `ViewId` (see
+/// `EmbeddingManager`'s doc comment for why).
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+struct ChunkKey {
+    doc_id: String,
+    start_line: usize,
+    end_line: usize,
+}
+
+/// The in-memory vector index, mirrored to SQLite by [`EmbeddingManager`].
+#[derive(Debug, Clone, Default)]
+struct SemanticIndex {
+    entries: HashMap<ChunkKey, IndexedChunk>,
+}
+
+impl SemanticIndex {
+    fn get_hash(&self, key: &ChunkKey) -> Option<u64> {
+        self.entries.get(key).map(|e| e.text_hash)
+    }
+
+    fn upsert(&mut self, key: ChunkKey, vector: Vec<f32>, text_hash: u64, snippet: String) {
+        self.entries.insert(
+            key,
+            IndexedChunk {
+                vector,
+                text_hash,
+                snippet,
+            },
+        );
+    }
+
+    /// Drops every entry for `doc_id` whose `(start_line, end_line)` isn't in
+    /// `live_keys`, returning the keys removed so the caller can prune the
+    /// same rows from the SQLite mirror. Without this, a chunk whose range
+    /// shifted (or that `chunk_lines` no longer produces because the buffer
+    /// shrank) stays in the index forever and keeps surfacing in `query`.
+    fn retain_doc(
+        &mut self,
+        doc_id: &str,
+        live_keys: &std::collections::HashSet<(usize, usize)>,
+    ) -> Vec<ChunkKey> {
+        let stale: Vec<ChunkKey> = self
+            .entries
+            .keys()
+            .filter(|key| {
+                key.doc_id == doc_id && !live_keys.contains(&(key.start_line, key.end_line))
+            })
+            .cloned()
+            .collect();
+        for key in &stale {
+            self.entries.remove(key);
+        }
+        stale
+    }
+
+    fn query(&self, doc_id: &str, query_vector: &[f32], top_k: usize) -> Vec<SemanticResultRow> {
+        let mut scored: Vec<(f32, &ChunkKey, &IndexedChunk)> = self
+            .entries
+            .iter()
+            .filter(|(key, _)| key.doc_id == doc_id)
+            .map(|(key, chunk)| (cosine(query_vector, &chunk.vector), key, chunk))
+            .collect();
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        scored
+            .into_iter()
+            .take(top_k)
+            .map(|(score, key, chunk)| SemanticResultRow {
+                start_line: key.start_line,
+                end_line: key.end_line,
+                score: score as f64,
+                snippet: chunk.snippet.clone(),
+            })
+            .collect()
+    }
+}
+
+/// A snippet-only projection of the buffer, kept in sync with `update` the
+/// same way `crate::line_cache::LineCache` is, so chunking has plain text to
+/// work with without this module depending on `Line`'s cursor/style fields.
+fn buffer_lines(cache: &LineCache) -> Vec<Option<String>> {
+    (0..cache.height())
+        .map(|ix| cache.get(ix).map(|line| line.text.clone()))
+        .collect()
+}
+
+/// Owns the embedding client, the per-document line cache used for chunking,
+/// the index, and the SQLite mirror, and delivers query results and the
+/// best-match `ScrollTo` back to the focused `EditView`.
+///
+/// Indexed by a stable per-document id (the file path) rather than xi-core's
+/// `ViewId`: xi-core hands out a fresh `ViewId` for every `new_view` call, so
+/// keying the SQLite cache by it would mean every chunk gets silently
+/// re-embedded on the next launch, defeating the cache entirely. Buffers with
+/// no path yet (never saved) fall back to their `ViewId` for the session,
+/// since there's no stable identity to persist them under anyway.
+#[derive(Clone)]
+pub struct EmbeddingManager {
+    client: EmbeddingClient,
+    db: Arc<Mutex<rusqlite::Connection>>,
+    index: Arc<Mutex<SemanticIndex>>,
+    views: Arc<Mutex<HashMap<ViewId, LineCache>>>,
+    sink: Arc<Mutex<Option<ExtEventSink>>>,
+    edit_view_id: WidgetId,
+}
+
+impl EmbeddingManager {
+    pub fn new(edit_view_id: WidgetId, endpoint: String, db_path: &str) -> EmbeddingManager {
+        let db = crate::semantic_store::open(db_path);
+        let mut index = SemanticIndex::default();
+        for (doc_id, start_line, end_line, text_hash, vector, snippet) in
+            crate::semantic_store::load_all(&db)
+        {
+            index.upsert(
+                ChunkKey {
+                    doc_id,
+                    start_line,
+                    end_line,
+                },
+                vector,
+                text_hash,
+                snippet,
+            );
+        }
+        EmbeddingManager {
+            client: EmbeddingClient::new(endpoint),
+            db: Arc::new(Mutex::new(db)),
+            index: Arc::new(Mutex::new(index)),
+            views: Default::default(),
+            sink: Default::default(),
+            edit_view_id,
+        }
+    }
+
+    pub fn set_sink(&self, sink: ExtEventSink) {
+        *self.sink.lock().unwrap() = Some(sink);
+    }
+
+    /// Replays an `update` notification's `ops` into this document's line
+    /// cache, then re-embeds only the chunks whose text actually changed
+    /// (detected by hash) rather than the whole buffer, and prunes any
+    /// chunk `chunk_lines` no longer produces (the buffer shrank, or the
+    /// chunk's line range shifted) from both the index and the SQLite
+    /// mirror. `view_id` is xi-core's session-local id, only used here to
+    /// track which buffer the update belongs to; `doc_id` is the stable
+    /// identity chunks are persisted under.
+    pub fn apply_update(&self, view_id: &str, doc_id: &str, update: &Value) {
+        {
+            let mut views = self.views.lock().unwrap();
+            views
+                .entry(view_id.to_string())
+                .or_default()
+                .apply_update(update);
+        }
+
+        let view_id = view_id.to_string();
+        let doc_id = doc_id.to_string();
+        let client = self.client.clone();
+        let db = self.db.clone();
+        let index = self.index.clone();
+        let views = self.views.clone();
+
+        std::thread::spawn(move || {
+            let lines = {
+                let views = views.lock().unwrap();
+                match views.get(&view_id) {
+                    Some(cache) => buffer_lines(cache),
+                    None => return,
+                }
+            };
+
+            let chunks = chunk_lines(&lines);
+            let live_keys: std::collections::HashSet<(usize, usize)> = chunks
+                .iter()
+                .map(|chunk| (chunk.start_line, chunk.end_line))
+                .collect();
+            let stale = index.lock().unwrap().retain_doc(&doc_id, &live_keys);
+            for key in &stale {
+                crate::semantic_store::delete_chunk(
+                    &db.lock().unwrap(),
+                    &key.doc_id,
+                    key.start_line,
+                    key.end_line,
+                );
+            }
+
+            for chunk in chunks {
+                let key = ChunkKey {
+                    doc_id: doc_id.clone(),
+                    start_line: chunk.start_line,
+                    end_line: chunk.end_line,
+                };
+                let hash = text_hash(&chunk.text);
+                if index.lock().unwrap().get_hash(&key) == Some(hash) {
+                    continue; // unchanged since last embed, skip the endpoint call
+                }
+                let vector = match client.embed(&chunk.text) {
+                    Some(vector) => vector,
+                    None => continue,
+                };
+                let snippet: String = chunk
+                    .text
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .chars()
+                    .take(80)
+                    .collect();
+
+                index
+                    .lock()
+                    .unwrap()
+                    .upsert(key.clone(), vector.clone(), hash, snippet.clone());
+                crate::semantic_store::save_chunk(
+                    &db.lock().unwrap(),
+                    &key.doc_id,
+                    key.start_line,
+                    key.end_line,
+                    hash,
+                    &vector,
+                    &snippet,
+                );
+            }
+        });
+    }
+
+    /// Embeds `query`, ranks every chunk in `doc_id` by cosine similarity,
+    /// pushes the top `top_k` into the root `ViewState` for the results
+    /// list, and scrolls the view to the best match.
+    pub fn query(&self, doc_id: &str, query: &str, top_k: usize) {
+        let doc_id = doc_id.to_string();
+        let query = query.to_string();
+        let client = self.client.clone();
+        let index = self.index.clone();
+        let sink = self.sink.clone();
+        let edit_view_id = self.edit_view_id;
+
+        std::thread::spawn(move || {
+            let query_vector = match client.embed(&query) {
+                Some(vector) => vector,
+                None => return,
+            };
+            let results = index.lock().unwrap().query(&doc_id, &query_vector, top_k);
+
+            if let Some(sink) = sink.lock().unwrap().as_ref() {
+                let _ = sink.submit_command(
+                    crate::UPDATE_VIEW_STATUS,
+                    ViewStatus {
+                        semantic_results: Some(im::Vector::from(results.clone())),
+                        ..ViewStatus::none()
+                    },
+                    Target::Global,
+                );
+                if let Some(best) = results.first() {
+                    let _ = sink.submit_command(
+                        EDIT_VIEW_COMMAND,
+                        EditViewCommands::ScrollTo(best.start_line),
+                        Target::Widget(edit_view_id),
+                    );
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(texts: &[&str]) -> Vec<Option<String>> {
+        texts.iter().map(|t| Some(t.to_string())).collect()
+    }
+
+    #[test]
+    fn chunk_lines_is_empty_for_an_empty_buffer() {
+        assert!(chunk_lines(&[]).is_empty());
+    }
+
+    #[test]
+    fn chunk_lines_skips_windows_with_an_unfetched_line() {
+        let mut buffer = lines(&["a"; 10]);
+        buffer[3] = None;
+        // Every window of CHUNK_LINES starting at or before line 3 still
+        // contains the unfetched line, so none of them should chunk.
+        assert!(chunk_lines(&buffer).is_empty());
+    }
+
+    #[test]
+    fn chunk_lines_overlaps_consecutive_windows() {
+        let texts: Vec<String> = (0..100).map(|i| i.to_string()).collect();
+        let refs: Vec<&str> = texts.iter().map(String::as_str).collect();
+        let buffer = lines(&refs);
+
+        let chunks = chunk_lines(&buffer);
+        assert!(chunks.len() >= 2);
+        assert_eq!(chunks[0].start_line, 0);
+        assert_eq!(chunks[0].end_line, CHUNK_LINES - 1);
+        // Consecutive chunks overlap by CHUNK_OVERLAP lines.
+        assert_eq!(chunks[1].start_line, CHUNK_LINES - CHUNK_OVERLAP);
+        // The last chunk always reaches the end of the buffer.
+        assert_eq!(chunks.last().unwrap().end_line, buffer.len() - 1);
+    }
+
+    #[test]
+    fn normalize_produces_a_unit_vector() {
+        let mut v = vec![3.0, 4.0];
+        normalize(&mut v);
+        assert!((v[0] - 0.6).abs() < 1e-6);
+        assert!((v[1] - 0.8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn normalize_leaves_a_zero_vector_alone() {
+        let mut v = vec![0.0, 0.0];
+        normalize(&mut v);
+        assert_eq!(v, vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn cosine_of_normalized_identical_vectors_is_one() {
+        let mut a = vec![1.0, 2.0, 3.0];
+        normalize(&mut a);
+        let b = a.clone();
+        assert!((cosine(&a, &b) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn cosine_of_orthogonal_vectors_is_zero() {
+        assert_eq!(cosine(&[1.0, 0.0], &[0.0, 1.0]), 0.0);
+    }
+
+    #[test]
+    fn semantic_index_query_ranks_by_similarity_and_scopes_by_doc() {
+        let mut index = SemanticIndex::default();
+        index.upsert(
+            ChunkKey {
+                doc_id: "a.rs".to_string(),
+                start_line: 0,
+                end_line: 9,
+            },
+            vec![1.0, 0.0],
+            1,
+            "best match".to_string(),
+        );
+        index.upsert(
+            ChunkKey {
+                doc_id: "a.rs".to_string(),
+                start_line: 10,
+                end_line: 19,
+            },
+            vec![0.0, 1.0],
+            2,
+            "worst match".to_string(),
+        );
+        index.upsert(
+            ChunkKey {
+                doc_id: "b.rs".to_string(),
+                start_line: 0,
+                end_line: 9,
+            },
+            vec![1.0, 0.0],
+            3,
+            "other doc".to_string(),
+        );
+
+        let results = index.query("a.rs", &[1.0, 0.0], 5);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].snippet, "best match");
+        assert_eq!(results[1].snippet, "worst match");
+    }
+
+    #[test]
+    fn retain_doc_drops_entries_not_in_live_keys_and_leaves_other_docs_alone() {
+        let mut index = SemanticIndex::default();
+        index.upsert(
+            ChunkKey {
+                doc_id: "a.rs".to_string(),
+                start_line: 0,
+                end_line: 9,
+            },
+            vec![1.0],
+            1,
+            "kept".to_string(),
+        );
+        index.upsert(
+            ChunkKey {
+                doc_id: "a.rs".to_string(),
+                start_line: 10,
+                end_line: 19,
+            },
+            vec![1.0],
+            2,
+            "stale".to_string(),
+        );
+        index.upsert(
+            ChunkKey {
+                doc_id: "b.rs".to_string(),
+                start_line: 10,
+                end_line: 19,
+            },
+            vec![1.0],
+            3,
+            "other doc, untouched".to_string(),
+        );
+
+        let live_keys: std::collections::HashSet<(usize, usize)> = [(0, 9)].into_iter().collect();
+        let removed = index.retain_doc("a.rs", &live_keys);
+
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].start_line, 10);
+        assert_eq!(index.query("a.rs", &[1.0], 5).len(), 1);
+        assert_eq!(index.query("b.rs", &[1.0], 5).len(), 1);
+    }
+}
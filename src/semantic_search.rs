@@ -0,0 +1,74 @@
+//! The natural-language search bar layered above the `EditView`, alongside
+//! [`crate::find::build_search_bar`]. Selecting a result posts the same
+//! `ScrollTo` command `Find` would use to jump to a literal match, so both
+//! bars drive the view through one path.
+
+use druid::widget::{Flex, Label, List, TextBox};
+use druid::{Env, Event, EventCtx, Selector, Widget, WidgetExt};
+
+use crate::menu::APP_VIEW_COMMAND;
+use crate::{EditViewCommands, SemanticResultRow, ViewState};
+
+/// Toggles `ViewState::semantic_open`; handled directly by the
+/// `AppDelegate`, the same way `crate::find::TOGGLE_FIND` is.
+pub const TOGGLE_SEMANTIC_SEARCH: Selector<()> = Selector::new("druid-xi.toggle-semantic-search");
+
+pub fn build_semantic_search_bar() -> impl Widget<ViewState> {
+    let query = TextBox::new()
+        .with_placeholder("Ask in plain language…")
+        .lens(ViewState::semantic_query)
+        .controller(SemanticSearchController)
+        .expand_width();
+
+    let results = List::new(|| {
+        Label::new(|row: &SemanticResultRow, _: &Env| {
+            format!(
+                "L{}-{} ({:.2}) {}",
+                row.start_line + 1,
+                row.end_line + 1,
+                row.score,
+                row.snippet
+            )
+        })
+        .on_click(|ctx, row: &mut SemanticResultRow, _| {
+            ctx.submit_command(APP_VIEW_COMMAND.with(EditViewCommands::ScrollTo(row.start_line)));
+        })
+    })
+    .lens(ViewState::semantic_results);
+
+    Flex::column()
+        .with_child(query)
+        .with_child(results)
+        .padding(4.0)
+}
+
+/// Translates Enter in the query box into a `SemanticSearch` request instead
+/// of inserting a newline, mirroring `crate::find::FindController`.
+struct SemanticSearchController;
+
+impl<W: Widget<ViewState>> druid::widget::Controller<ViewState, W> for SemanticSearchController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut ViewState,
+        env: &Env,
+    ) {
+        if let Event::KeyDown(key_event) = event {
+            if key_event.key == druid::KbKey::Enter && !data.semantic_query.is_empty() {
+                ctx.submit_command(APP_VIEW_COMMAND.with(EditViewCommands::SemanticSearch(
+                    data.semantic_query.clone(),
+                )));
+                ctx.set_handled();
+                return;
+            }
+            if key_event.key == druid::KbKey::Escape {
+                data.semantic_open = false;
+                ctx.set_handled();
+                return;
+            }
+        }
+        child.event(ctx, event, data, env);
+    }
+}
@@ -0,0 +1,148 @@
+//! Persists [`crate::semantic`]'s chunk embeddings to a local SQLite file, so
+//! reopening a buffer doesn't have to re-embed chunks whose text hasn't
+//! changed since the last session.
+
+use rusqlite::{params, Connection};
+
+/// Opens (creating if necessary) the embedding cache at `path` and ensures
+/// its single `chunks` table exists.
+pub fn open(path: &str) -> Connection {
+    let conn = Connection::open(path).expect("failed to open semantic search cache");
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS chunks (
+            doc_id     TEXT NOT NULL,
+            start_line INTEGER NOT NULL,
+            end_line   INTEGER NOT NULL,
+            text_hash  INTEGER NOT NULL,
+            vector     BLOB NOT NULL,
+            snippet    TEXT NOT NULL,
+            PRIMARY KEY (doc_id, start_line, end_line)
+        )",
+        [],
+    )
+    .expect("failed to create chunks table");
+    conn
+}
+
+fn encode_vector(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn decode_vector(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+/// Loads every cached chunk, as `(doc_id, start_line, end_line, text_hash,
+/// vector, snippet)` tuples, for `EmbeddingManager` to seed its in-memory
+/// index from at startup.
+pub fn load_all(conn: &Connection) -> Vec<(String, usize, usize, u64, Vec<f32>, String)> {
+    let mut stmt = match conn
+        .prepare("SELECT doc_id, start_line, end_line, text_hash, vector, snippet FROM chunks")
+    {
+        Ok(stmt) => stmt,
+        Err(_) => return Vec::new(),
+    };
+    let rows = stmt.query_map([], |row| {
+        let vector: Vec<u8> = row.get(4)?;
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, i64>(1)? as usize,
+            row.get::<_, i64>(2)? as usize,
+            row.get::<_, i64>(3)? as u64,
+            decode_vector(&vector),
+            row.get::<_, String>(5)?,
+        ))
+    });
+    match rows {
+        Ok(rows) => rows.filter_map(Result::ok).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Upserts one chunk's embedding, replacing whatever was cached for the same
+/// `(doc_id, start_line, end_line)`.
+pub fn save_chunk(
+    conn: &Connection,
+    doc_id: &str,
+    start_line: usize,
+    end_line: usize,
+    text_hash: u64,
+    vector: &[f32],
+    snippet: &str,
+) {
+    let _ = conn.execute(
+        "INSERT OR REPLACE INTO chunks (doc_id, start_line, end_line, text_hash, vector, snippet)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            doc_id,
+            start_line as i64,
+            end_line as i64,
+            text_hash as i64,
+            encode_vector(vector),
+            snippet,
+        ],
+    );
+}
+
+/// Removes one cached chunk, e.g. when the buffer shrinks or a chunk's line
+/// range shifts so `chunk_lines` no longer produces it for this `doc_id`.
+pub fn delete_chunk(conn: &Connection, doc_id: &str, start_line: usize, end_line: usize) {
+    let _ = conn.execute(
+        "DELETE FROM chunks WHERE doc_id = ?1 AND start_line = ?2 AND end_line = ?3",
+        params![doc_id, start_line as i64, end_line as i64],
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vector_round_trips_through_encode_decode() {
+        let vector = vec![0.5, -1.25, 3.0];
+        assert_eq!(decode_vector(&encode_vector(&vector)), vector);
+    }
+
+    #[test]
+    fn save_chunk_and_load_all_round_trip() {
+        let conn = open(":memory:");
+        save_chunk(&conn, "a.rs", 0, 9, 42, &[1.0, 2.0], "first chunk");
+
+        let rows = load_all(&conn);
+        assert_eq!(rows.len(), 1);
+        let (doc_id, start_line, end_line, text_hash, vector, snippet) = &rows[0];
+        assert_eq!(doc_id, "a.rs");
+        assert_eq!(*start_line, 0);
+        assert_eq!(*end_line, 9);
+        assert_eq!(*text_hash, 42);
+        assert_eq!(vector, &vec![1.0, 2.0]);
+        assert_eq!(snippet, "first chunk");
+    }
+
+    #[test]
+    fn save_chunk_replaces_the_same_key() {
+        let conn = open(":memory:");
+        save_chunk(&conn, "a.rs", 0, 9, 1, &[1.0], "old");
+        save_chunk(&conn, "a.rs", 0, 9, 2, &[2.0], "new");
+
+        let rows = load_all(&conn);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].5, "new");
+    }
+
+    #[test]
+    fn delete_chunk_removes_only_the_matching_key() {
+        let conn = open(":memory:");
+        save_chunk(&conn, "a.rs", 0, 9, 1, &[1.0], "stays");
+        save_chunk(&conn, "a.rs", 10, 19, 2, &[2.0], "goes");
+
+        delete_chunk(&conn, "a.rs", 10, 19);
+
+        let rows = load_all(&conn);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].5, "stays");
+    }
+}
@@ -0,0 +1,147 @@
+//! Parses the theme data xi-core sends via `theme_changed`, and exposes it
+//! both as base colors for the druid `Env`
+//! and as a per-style-id map the `EditView` resolves line styles against.
+
+use std::collections::HashMap;
+
+use druid::{Color, Env, Key};
+use serde_json::Value;
+
+/// Base editor colors injected into the `Env` so any widget can pick them up
+/// the same way it would a built-in druid theme color.
+pub const EDITOR_FOREGROUND: Key<Color> = Key::new("druid-xi.theme.foreground");
+pub const EDITOR_BACKGROUND: Key<Color> = Key::new("druid-xi.theme.background");
+pub const EDITOR_CARET: Key<Color> = Key::new("druid-xi.theme.caret");
+pub const EDITOR_SELECTION: Key<Color> = Key::new("druid-xi.theme.selection");
+
+/// The fg color and font style of one entry in xi-core's numeric style map,
+/// referenced by `style_id` from each line's `styles` spans.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StyleDef {
+    pub fg: Option<Color>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+impl StyleDef {
+    fn from_json(value: &Value) -> StyleDef {
+        let fg = value.get("fg_color").and_then(parse_color);
+        // xi-core packs bold/italic/underline into a `font_style` bitmask:
+        // 1 = italic, 2 = bold, 4 = underline.
+        let font_style = value["font_style"].as_u64().unwrap_or(0);
+        StyleDef {
+            fg,
+            italic: font_style & 1 != 0,
+            bold: font_style & 2 != 0,
+            underline: font_style & 4 != 0,
+        }
+    }
+}
+
+/// A fully parsed xi-core theme: base colors plus the numeric style map used
+/// to color `update` line spans.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    pub foreground: Color,
+    pub background: Color,
+    pub caret: Color,
+    pub selection: Color,
+    pub styles: HashMap<usize, StyleDef>,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme {
+            name: "InspiredGitHub".into(),
+            foreground: Color::WHITE,
+            background: Color::BLACK,
+            caret: Color::WHITE,
+            selection: Color::rgba8(0x3d, 0x59, 0x7e, 0xff),
+            styles: HashMap::new(),
+        }
+    }
+}
+
+impl Theme {
+    /// Parses a `theme_changed` notification's `{name, theme}` payload. Its
+    /// `theme` object carries `foreground`/`background`/`caret`/`selection`
+    /// as `{r, g, b, a}` and a `styles` array of `{id, style}` entries.
+    pub fn from_json(name: &str, settings: &Value) -> Theme {
+        let mut theme = Theme {
+            name: name.to_string(),
+            ..Theme::default()
+        };
+
+        if let Some(c) = parse_color(&settings["foreground"]) {
+            theme.foreground = c;
+        }
+        if let Some(c) = parse_color(&settings["background"]) {
+            theme.background = c;
+        }
+        if let Some(c) = parse_color(&settings["caret"]) {
+            theme.caret = c;
+        }
+        if let Some(c) = parse_color(&settings["selection"]) {
+            theme.selection = c;
+        }
+
+        if let Some(styles) = settings["styles"].as_array() {
+            for entry in styles {
+                if let Some(id) = entry["id"].as_u64() {
+                    theme
+                        .styles
+                        .insert(id as usize, StyleDef::from_json(&entry["style"]));
+                }
+            }
+        }
+
+        theme
+    }
+
+    /// Installs the base colors into the `Env` so widgets read them the same
+    /// way they'd read a built-in druid theme color.
+    pub fn configure_env(&self, env: &mut Env) {
+        env.set(EDITOR_FOREGROUND, self.foreground.clone());
+        env.set(EDITOR_BACKGROUND, self.background.clone());
+        env.set(EDITOR_CARET, self.caret.clone());
+        env.set(EDITOR_SELECTION, self.selection.clone());
+    }
+}
+
+fn parse_color(value: &Value) -> Option<Color> {
+    let r = value.get("r")?.as_u64()? as u8;
+    let g = value.get("g")?.as_u64()? as u8;
+    let b = value.get("b")?.as_u64()? as u8;
+    let a = value.get("a").and_then(Value::as_u64).unwrap_or(255) as u8;
+    Some(Color::rgba8(r, g, b, a))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_base_colors_and_styles() {
+        let theme = Theme::from_json(
+            "InspiredGitHub",
+            &json!({
+                "foreground": { "r": 10, "g": 20, "b": 30, "a": 255 },
+                "background": { "r": 255, "g": 255, "b": 255, "a": 255 },
+                "caret": { "r": 0, "g": 0, "b": 0, "a": 255 },
+                "selection": { "r": 200, "g": 200, "b": 200, "a": 255 },
+                "styles": [
+                    { "id": 1, "style": { "fg_color": { "r": 255, "g": 0, "b": 0, "a": 255 }, "font_style": 2 } },
+                ],
+            }),
+        );
+
+        assert_eq!(theme.foreground, Color::rgba8(10, 20, 30, 255));
+        let comment_style = theme.styles.get(&1).unwrap();
+        assert_eq!(comment_style.fg, Some(Color::rgba8(255, 0, 0, 255)));
+        assert!(comment_style.bold);
+        assert!(!comment_style.italic);
+    }
+}